@@ -0,0 +1,16 @@
+-- Revert full-text and fuzzy search support
+
+DROP INDEX IF EXISTS idx_contacts_company_trgm;
+DROP INDEX IF EXISTS idx_contacts_last_name_trgm;
+DROP INDEX IF EXISTS idx_contacts_first_name_trgm;
+DROP INDEX IF EXISTS idx_users_last_name_trgm;
+DROP INDEX IF EXISTS idx_users_first_name_trgm;
+DROP INDEX IF EXISTS idx_users_username_trgm;
+
+DROP INDEX IF EXISTS idx_contacts_search_vector;
+DROP INDEX IF EXISTS idx_users_search_vector;
+
+ALTER TABLE contacts DROP COLUMN IF EXISTS search_vector;
+ALTER TABLE users DROP COLUMN IF EXISTS search_vector;
+
+-- Note: the pg_trgm extension is left installed, other objects may depend on it
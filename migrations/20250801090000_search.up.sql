@@ -0,0 +1,39 @@
+-- Full-text and fuzzy search support for users and contacts
+-- The 'simple' configuration (no stemming, no stop words) suits names and addresses in any language
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Generated tsvector columns used by search_mode=fts
+ALTER TABLE users ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+    to_tsvector('simple',
+        coalesce(username, '') || ' ' ||
+        coalesce(email, '') || ' ' ||
+        coalesce(first_name, '') || ' ' ||
+        coalesce(last_name, ''))
+) STORED;
+
+ALTER TABLE contacts ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+    to_tsvector('simple',
+        coalesce(first_name, '') || ' ' ||
+        coalesce(last_name, '') || ' ' ||
+        coalesce(email, '') || ' ' ||
+        coalesce(phone, '') || ' ' ||
+        coalesce(mobile, '') || ' ' ||
+        coalesce(company, '') || ' ' ||
+        coalesce(city, '') || ' ' ||
+        coalesce(state, '') || ' ' ||
+        coalesce(country, ''))
+) STORED;
+
+CREATE INDEX idx_users_search_vector ON users USING GIN (search_vector);
+CREATE INDEX idx_contacts_search_vector ON contacts USING GIN (search_vector);
+
+-- Trigram indexes used by search_mode=fuzzy (and by ILIKE '%term%' search)
+CREATE INDEX idx_users_username_trgm ON users USING GIN (username gin_trgm_ops);
+CREATE INDEX idx_users_first_name_trgm ON users USING GIN (first_name gin_trgm_ops);
+CREATE INDEX idx_users_last_name_trgm ON users USING GIN (last_name gin_trgm_ops);
+CREATE INDEX idx_contacts_first_name_trgm ON contacts USING GIN (first_name gin_trgm_ops);
+CREATE INDEX idx_contacts_last_name_trgm ON contacts USING GIN (last_name gin_trgm_ops);
+CREATE INDEX idx_contacts_company_trgm ON contacts USING GIN (company gin_trgm_ops);
+
+COMMENT ON COLUMN users.search_vector IS 'Generated full-text search document';
+COMMENT ON COLUMN contacts.search_vector IS 'Generated full-text search document';
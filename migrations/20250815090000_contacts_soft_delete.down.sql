@@ -0,0 +1,7 @@
+-- Revert soft delete for contacts (trashed contacts become visible again)
+
+DROP INDEX IF EXISTS idx_contacts_deleted_at;
+
+ALTER TABLE contacts DROP CONSTRAINT IF EXISTS fk_contacts_deleted_by;
+ALTER TABLE contacts DROP COLUMN IF EXISTS deleted_by;
+ALTER TABLE contacts DROP COLUMN IF EXISTS deleted_at;
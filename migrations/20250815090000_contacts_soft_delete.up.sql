@@ -0,0 +1,12 @@
+-- Soft delete for contacts: deleting only stamps deleted_at/deleted_by, since
+-- contacts stay referenced by accounting records
+ALTER TABLE contacts ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE contacts ADD COLUMN deleted_by UUID;
+
+ALTER TABLE contacts ADD CONSTRAINT fk_contacts_deleted_by FOREIGN KEY (deleted_by) REFERENCES users(id);
+
+-- Lists filter on deleted_at IS NULL by default; trashed=only reads the (small) trash
+CREATE INDEX idx_contacts_deleted_at ON contacts(deleted_at) WHERE deleted_at IS NOT NULL;
+
+COMMENT ON COLUMN contacts.deleted_at IS 'When the contact was moved to the trash';
+COMMENT ON COLUMN contacts.deleted_by IS 'Who moved the contact to the trash';
@@ -0,0 +1,3 @@
+-- Drop document-code counters (codes already assigned are kept)
+
+DROP TABLE IF EXISTS code_sequences;
@@ -0,0 +1,33 @@
+-- Counters behind generated document codes (U-00001, D-00001, ...). One row per
+-- table, prefix and period ('' unless the sequence restarts every year). Rows are
+-- incremented inside the insert transaction, so a rolled back insert frees its number.
+CREATE TABLE code_sequences (
+    entity VARCHAR(50) NOT NULL,
+    prefix VARCHAR(20) NOT NULL,
+    period VARCHAR(10) NOT NULL DEFAULT '',
+    last_value BIGINT NOT NULL DEFAULT 0 CHECK (last_value >= 0),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_by UUID,
+
+    PRIMARY KEY (entity, prefix, period),
+    CONSTRAINT fk_code_sequences_updated_by FOREIGN KEY (updated_by) REFERENCES users(id)
+);
+
+-- Continue after the codes already in use
+INSERT INTO code_sequences (entity, prefix, last_value)
+SELECT 'users', split_part(code, '-', 1), MAX(split_part(code, '-', 2)::BIGINT)
+FROM users
+WHERE code ~ '^[A-Z]+-[0-9]+$'
+GROUP BY split_part(code, '-', 1);
+
+INSERT INTO code_sequences (entity, prefix, last_value)
+SELECT 'contacts', split_part(code, '-', 1), MAX(split_part(code, '-', 2)::BIGINT)
+FROM contacts
+WHERE code ~ '^[A-Z]+-[0-9]+$'
+GROUP BY split_part(code, '-', 1);
+
+COMMENT ON TABLE code_sequences IS 'Last number allocated per document-code sequence';
+COMMENT ON COLUMN code_sequences.entity IS 'Table the codes belong to';
+COMMENT ON COLUMN code_sequences.prefix IS 'Code prefix, e.g. U or D';
+COMMENT ON COLUMN code_sequences.period IS 'Year for yearly sequences, empty otherwise';
+COMMENT ON COLUMN code_sequences.last_value IS 'Last number handed out; the next code uses last_value + 1';
@@ -0,0 +1,79 @@
+-- Role-based access control. Permissions are named `<resource>:<action>`
+-- (actions: read, write, delete, purge); `*` and `<resource>:*` grant every
+-- permission, or every action on one resource.
+CREATE TABLE roles (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(50) NOT NULL UNIQUE,
+    description TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE permissions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL UNIQUE,
+    description TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE role_permissions (
+    role_id UUID NOT NULL,
+    permission_id UUID NOT NULL,
+
+    PRIMARY KEY (role_id, permission_id),
+    CONSTRAINT fk_role_permissions_role_id FOREIGN KEY (role_id) REFERENCES roles(id) ON DELETE CASCADE,
+    CONSTRAINT fk_role_permissions_permission_id FOREIGN KEY (permission_id) REFERENCES permissions(id) ON DELETE CASCADE
+);
+
+CREATE TABLE user_roles (
+    user_id UUID NOT NULL,
+    role_id UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by UUID,
+
+    PRIMARY KEY (user_id, role_id),
+    CONSTRAINT fk_user_roles_user_id FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CONSTRAINT fk_user_roles_role_id FOREIGN KEY (role_id) REFERENCES roles(id) ON DELETE CASCADE,
+    CONSTRAINT fk_user_roles_created_by FOREIGN KEY (created_by) REFERENCES users(id)
+);
+
+CREATE INDEX idx_user_roles_role_id ON user_roles(role_id);
+CREATE INDEX idx_role_permissions_permission_id ON role_permissions(permission_id);
+
+INSERT INTO permissions (name, description) VALUES
+('*', 'Every permission'),
+('users:read', 'List and view users'),
+('users:write', 'Create and update users'),
+('users:delete', 'Delete users'),
+('contacts:read', 'List and view contacts'),
+('contacts:write', 'Create, update and restore contacts'),
+('contacts:delete', 'Move contacts to the trash'),
+('contacts:purge', 'Permanently delete trashed contacts'),
+('code_sequences:read', 'View document-code sequences'),
+('code_sequences:write', 'Reset document-code sequences');
+
+INSERT INTO roles (name, description) VALUES
+('admin', 'Full access'),
+('staff', 'Maintains contacts and looks up users');
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT r.id, p.id FROM roles r JOIN permissions p ON p.name = '*'
+WHERE r.name = 'admin';
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT r.id, p.id FROM roles r
+JOIN permissions p ON p.name IN ('contacts:read', 'contacts:write', 'contacts:delete', 'users:read')
+WHERE r.name = 'staff';
+
+-- Sample users: admin gets admin, testuser gets staff
+INSERT INTO user_roles (user_id, role_id)
+SELECT u.id, r.id FROM users u JOIN roles r ON r.name = 'admin'
+WHERE u.username = 'admin';
+
+INSERT INTO user_roles (user_id, role_id)
+SELECT u.id, r.id FROM users u JOIN roles r ON r.name = 'staff'
+WHERE u.username = 'testuser';
+
+COMMENT ON TABLE roles IS 'Named sets of permissions assigned to users';
+COMMENT ON TABLE permissions IS 'Permission names checked by routes, e.g. contacts:write';
+COMMENT ON TABLE role_permissions IS 'Permissions each role grants';
+COMMENT ON TABLE user_roles IS 'Roles each user holds';
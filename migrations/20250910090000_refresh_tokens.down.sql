@@ -0,0 +1,3 @@
+-- Drop refresh tokens (every session has to log in again)
+
+DROP TABLE IF EXISTS refresh_tokens;
@@ -0,0 +1,5 @@
+-- Drop login sessions (refresh token families are kept)
+
+DELETE FROM permissions WHERE name IN ('sessions:read', 'sessions:delete');
+ALTER TABLE refresh_tokens DROP CONSTRAINT IF EXISTS fk_refresh_tokens_family_id;
+DROP TABLE IF EXISTS sessions;
@@ -0,0 +1,33 @@
+-- Login sessions. A session is one refresh token family: `refresh_tokens.family_id`
+-- is the session id, and access tokens carry it as their `sid` claim. Revoking a
+-- session revokes its refresh tokens and rejects its access tokens at once.
+CREATE TABLE sessions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    device VARCHAR(100),
+    ip_address VARCHAR(45),
+    user_agent TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMPTZ,
+
+    CONSTRAINT fk_sessions_user_id FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_sessions_user_id ON sessions(user_id);
+
+-- Families issued before sessions existed
+INSERT INTO sessions (id, user_id, created_at, last_seen_at, revoked_at)
+SELECT family_id, user_id, MIN(created_at), MAX(created_at),
+       CASE WHEN bool_and(revoked_at IS NOT NULL) THEN MAX(revoked_at) END
+FROM refresh_tokens
+GROUP BY family_id, user_id;
+
+ALTER TABLE refresh_tokens
+ADD CONSTRAINT fk_refresh_tokens_family_id FOREIGN KEY (family_id) REFERENCES sessions(id) ON DELETE CASCADE;
+
+INSERT INTO permissions (name, description) VALUES
+('sessions:read', 'List the sessions of any user'),
+('sessions:delete', 'Revoke the sessions of any user');
+
+COMMENT ON TABLE sessions IS 'Logins, each owning a refresh token family';
@@ -0,0 +1,3 @@
+-- Allow mixed-case emails again (stored addresses stay lowercase)
+
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_email_lowercase;
@@ -0,0 +1,7 @@
+-- Emails are stored trimmed and lowercase, so the unique constraint on them is
+-- case-insensitive and login can look them up with a plain index scan. Fails if two
+-- existing addresses differ only in case; merge those accounts first.
+UPDATE users SET email = LOWER(TRIM(email)) WHERE email <> LOWER(TRIM(email));
+
+ALTER TABLE users
+    ADD CONSTRAINT users_email_lowercase CHECK (email = LOWER(TRIM(email)));
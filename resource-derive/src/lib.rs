@@ -0,0 +1,364 @@
+//! `#[derive(Resource)]` for model structs.
+//!
+//! Generates the column lists `QueryBuilder` consumes (as an impl of
+//! `crate::utils::resource::ResourceFields`) and, when asked for, the create/update
+//! request DTOs plus the conversion of a create DTO into the values `CrudService`
+//! inserts, and the response body with its conversion from the model.
+//!
+//! ```ignore
+//! #[derive(Resource)]
+//! #[resource(
+//!     table = "contacts",
+//!     create = CreateContactRequest,
+//!     update = UpdateContactRequest,
+//!     response = ContactResponse,
+//!     computed(full_name: String)
+//! )]
+//! pub struct Contact {
+//!     pub id: Uuid,
+//!     #[resource(searchable, sortable, create, update)]
+//!     pub first_name: String,
+//!     #[resource(filterable, create(default = false), update)]
+//!     pub is_customer: bool,
+//!     #[resource(skip)]
+//!     pub created_user: Option<UserSummary>,
+//! }
+//! ```
+//!
+//! The response has every field that isn't `hidden`, then each `computed` field,
+//! filled in from the model's method of that name.
+//!
+//! Field options:
+//! - `searchable`, `filterable`, `sortable`: add the column to that whitelist
+//! - `create`: the column is writable on create and in the create DTO;
+//!   `create(default = expr)` makes it optional there and inserts `expr` when omitted
+//! - `update`: the column is writable on update and in the update DTO as an `Option`
+//! - `hidden`: a column that is never selected or returned (e.g. `password_hash`)
+//! - `skip_if_none`: left out of the response while `None`
+//! - `skip`: not a column at all (e.g. an included relation); still returned, and
+//!   left out while `None`
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input,
+    spanned::Spanned,
+    Data, DeriveInput, Error, Expr, Fields, Ident, LitStr, Token, Type,
+};
+
+#[proc_macro_derive(Resource, attributes(resource))]
+pub fn derive_resource(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct ContainerOptions {
+    table: Option<LitStr>,
+    create: Option<Ident>,
+    update: Option<Ident>,
+    response: Option<Ident>,
+    computed: Vec<Computed>,
+}
+
+// `name: Type` of a `computed(...)` response field
+struct Computed {
+    ident: Ident,
+    ty: Type,
+}
+
+impl Parse for Computed {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ident = input.parse()?;
+        input.parse::<Token![:]>()?;
+        Ok(Self {
+            ident,
+            ty: input.parse()?,
+        })
+    }
+}
+
+#[derive(Default)]
+struct FieldOptions {
+    skip: bool,
+    hidden: bool,
+    skip_if_none: bool,
+    searchable: bool,
+    filterable: bool,
+    sortable: bool,
+    create: bool,
+    create_default: Option<Expr>,
+    update: bool,
+}
+
+struct Field {
+    ident: Ident,
+    ty: Type,
+    options: FieldOptions,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let container = container_options(&input)?;
+    let Some(table) = container.table else {
+        return Err(Error::new(
+            input.ident.span(),
+            "missing #[resource(table = \"...\")]",
+        ));
+    };
+
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            input.ident.span(),
+            "#[derive(Resource)] only supports structs",
+        ));
+    };
+    let Fields::Named(named) = &data.fields else {
+        return Err(Error::new(
+            input.ident.span(),
+            "#[derive(Resource)] needs named fields",
+        ));
+    };
+
+    let fields = named
+        .named
+        .iter()
+        .map(|field| {
+            Ok(Field {
+                ident: field.ident.clone().expect("named field"),
+                ty: field.ty.clone(),
+                options: field_options(field)?,
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let columns = |keep: fn(&FieldOptions) -> bool| -> Vec<String> {
+        fields
+            .iter()
+            .filter(|field| !field.options.skip && keep(&field.options))
+            .map(|field| field.ident.to_string())
+            .collect()
+    };
+    let select = columns(|options| !options.hidden);
+    let searchable = columns(|options| options.searchable);
+    let filterable = columns(|options| options.filterable);
+    let sortable = columns(|options| options.sortable);
+    let creatable = columns(|options| options.create);
+    let updatable = columns(|options| options.update);
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let mut output = quote! {
+        impl crate::utils::resource::ResourceFields for #name {
+            const TABLE: &'static str = #table;
+            const SELECT_FIELDS: &'static [&'static str] = &[#(#select),*];
+            const SEARCHABLE_FIELDS: &'static [&'static str] = &[#(#searchable),*];
+            const FILTERABLE_FIELDS: &'static [&'static str] = &[#(#filterable),*];
+            const SORTABLE_FIELDS: &'static [&'static str] = &[#(#sortable),*];
+            const CREATE_FIELDS: &'static [&'static str] = &[#(#creatable),*];
+            const UPDATE_FIELDS: &'static [&'static str] = &[#(#updatable),*];
+        }
+    };
+
+    if let Some(create) = &container.create {
+        let create_fields: Vec<&Field> = fields.iter().filter(|f| f.options.create).collect();
+        let declarations = create_fields.iter().map(|field| {
+            let ident = &field.ident;
+            let ty = if field.options.create_default.is_some() {
+                optional(&field.ty)
+            } else {
+                field.ty.clone()
+            };
+            quote! { pub #ident: #ty }
+        });
+        let inserts = create_fields.iter().map(|field| {
+            let ident = &field.ident;
+            let column = ident.to_string();
+            let value = match &field.options.create_default {
+                Some(default) if !is_option(&field.ty) => quote! {
+                    data.#ident.unwrap_or_else(|| ::core::convert::Into::into(#default))
+                },
+                Some(default) => quote! {
+                    data.#ident.or_else(|| Some(::core::convert::Into::into(#default)))
+                },
+                None => quote! { data.#ident },
+            };
+            quote! {
+                values.insert(#column.to_string(), ::serde_json::json!(#value));
+            }
+        });
+
+        output.extend(quote! {
+            #[derive(Debug, ::serde::Deserialize)]
+            #vis struct #create {
+                #(#declarations,)*
+            }
+
+            impl ::core::convert::From<#create> for ::serde_json::Value {
+                fn from(data: #create) -> Self {
+                    let mut values = ::serde_json::Map::new();
+                    #(#inserts)*
+                    ::serde_json::Value::Object(values)
+                }
+            }
+        });
+    }
+
+    if let Some(update) = &container.update {
+        let declarations = fields.iter().filter(|f| f.options.update).map(|field| {
+            let ident = &field.ident;
+            let ty = optional(&field.ty);
+            quote! { pub #ident: #ty }
+        });
+
+        output.extend(quote! {
+            #[derive(Debug, ::serde::Deserialize, ::serde::Serialize)]
+            #vis struct #update {
+                #(#declarations,)*
+            }
+        });
+    }
+
+    if let Some(response) = &container.response {
+        let returned: Vec<&Field> = fields.iter().filter(|f| !f.options.hidden).collect();
+        let declarations = returned.iter().map(|field| {
+            let ident = &field.ident;
+            let ty = &field.ty;
+            let skip_none = field.options.skip_if_none || (field.options.skip && is_option(ty));
+            let attr = skip_none.then(|| {
+                quote! { #[serde(skip_serializing_if = "Option::is_none")] }
+            });
+            quote! { #attr pub #ident: #ty }
+        });
+        let conversions = returned.iter().map(|field| {
+            let ident = &field.ident;
+            quote! { #ident: row.#ident }
+        });
+        let computed_declarations = container.computed.iter().map(|computed| {
+            let ident = &computed.ident;
+            let ty = &computed.ty;
+            quote! { pub #ident: #ty }
+        });
+        // Computed first: they borrow the row the other fields are moved out of
+        let computed_values = container.computed.iter().map(|computed| {
+            let ident = &computed.ident;
+            quote! { #ident: row.#ident() }
+        });
+
+        output.extend(quote! {
+            #[derive(Debug, ::serde::Serialize)]
+            #vis struct #response {
+                #(#declarations,)*
+                #(#computed_declarations,)*
+            }
+
+            impl ::core::convert::From<#name> for #response {
+                fn from(row: #name) -> Self {
+                    Self {
+                        #(#computed_values,)*
+                        #(#conversions,)*
+                    }
+                }
+            }
+        });
+    }
+
+    Ok(output)
+}
+
+fn container_options(input: &DeriveInput) -> syn::Result<ContainerOptions> {
+    let mut options = ContainerOptions::default();
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("resource")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("table") {
+                options.table = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("create") {
+                options.create = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("update") {
+                options.update = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("response") {
+                options.response = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("computed") {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                options
+                    .computed
+                    .extend(content.parse_terminated(Computed::parse, Token![,])?);
+            } else {
+                return Err(meta.error("unknown resource option"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(options)
+}
+
+fn field_options(field: &syn::Field) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("resource")) {
+        attr.parse_nested_meta(|meta| {
+            let path = &meta.path;
+            if path.is_ident("skip") {
+                options.skip = true;
+            } else if path.is_ident("hidden") {
+                options.hidden = true;
+            } else if path.is_ident("skip_if_none") {
+                options.skip_if_none = true;
+            } else if path.is_ident("searchable") {
+                options.searchable = true;
+            } else if path.is_ident("filterable") {
+                options.filterable = true;
+            } else if path.is_ident("sortable") {
+                options.sortable = true;
+            } else if path.is_ident("update") {
+                options.update = true;
+            } else if path.is_ident("create") {
+                options.create = true;
+                if meta.input.peek(syn::token::Paren) {
+                    meta.parse_nested_meta(|inner| {
+                        if inner.path.is_ident("default") {
+                            options.create_default = Some(inner.value()?.parse()?);
+                            Ok(())
+                        } else {
+                            Err(inner.error("expected `default = ...`"))
+                        }
+                    })?;
+                }
+            } else {
+                return Err(meta.error("unknown resource field option"));
+            }
+            Ok(())
+        })?;
+    }
+
+    if options.skip && (options.create || options.update || options.hidden) {
+        return Err(Error::new(
+            field.span(),
+            "a skipped field is not a column and can't be written",
+        ));
+    }
+    Ok(options)
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+// `T` as `Option<T>`, leaving types that already are an `Option` alone
+fn optional(ty: &Type) -> Type {
+    if is_option(ty) {
+        ty.clone()
+    } else {
+        syn::parse_quote! { Option<#ty> }
+    }
+}
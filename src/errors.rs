@@ -3,6 +3,7 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
+use serde::Serialize;
 use serde_json::json;
 use tracing::info;
 
@@ -13,10 +14,33 @@ pub enum AppError {
     BadRequest(String),
     DatabaseError(String),
     UnAuthorized,
+    Forbidden(String),
     CookieFormatError,
     NotFound { id: uuid::Uuid },
     SerializationError(String),
     InternalServerError(String),
+    BulkFailed(Vec<BulkItemError>),
+    PreconditionFailed,
+    InvalidFields(Vec<FieldError>),
+    Conflict(String),
+}
+
+/// Why a request body member can't be written.
+#[derive(Debug, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub error: String,
+}
+
+/// Why one item of a bulk request failed; `index` is its position in the request body.
+#[derive(Debug, Serialize)]
+pub struct BulkItemError {
+    pub index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<uuid::Uuid>,
+    pub error: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
 }
 
 pub type Result<T> = core::result::Result<T, AppError>;
@@ -49,6 +73,9 @@ impl IntoResponse for AppError {
                 "Unauthorized",
                 "You must be authenticated to access this resource."
             ),
+            AppError::Forbidden(details) => {
+                error_response!(StatusCode::FORBIDDEN, "Forbidden", details)
+            }
             AppError::CookieFormatError => error_response!(
                 StatusCode::BAD_REQUEST,
                 "Cookie Format Error",
@@ -79,6 +106,22 @@ impl IntoResponse for AppError {
                 "Internal Server Error",
                 details
             ),
+            AppError::PreconditionFailed => error_response!(
+                StatusCode::PRECONDITION_FAILED,
+                "Precondition Failed",
+                "The resource was modified since it was read; fetch it again and retry."
+            ),
+            AppError::InvalidFields(errors) => {
+                error_response!(StatusCode::BAD_REQUEST, "Invalid Fields", errors)
+            }
+            AppError::Conflict(details) => {
+                error_response!(StatusCode::CONFLICT, "Conflict", details)
+            }
+            AppError::BulkFailed(errors) => error_response!(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Bulk Operation Failed",
+                errors
+            ),
         }
     }
 }
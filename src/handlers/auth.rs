@@ -1,21 +1,33 @@
 use axum::{
-    extract::{rejection::JsonRejection, State},
-    http::StatusCode,
+    extract::{rejection::JsonRejection, ConnectInfo, State},
+    http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use bcrypt::verify;
 use serde::Deserialize;
 use serde_json::json;
-use tower_cookies::{Cookie, Cookies};
+use std::net::SocketAddr;
+use tower_cookies::{cookie::time::Duration, Cookie, Cookies};
 use tracing::info;
 
 use crate::{
+    handlers::registration::require_verified_email,
     models::{AppState, User},
-    utils::{constants::AUTH_TOKEN, JwtService},
+    utils::{
+        constants::{AUTH_TOKEN, REFRESH_TOKEN},
+        email,
+        jwt::ACCESS_TOKEN_TTL_MINUTES,
+        refresh_token::{IssuedRefreshToken, REFRESH_TOKEN_TTL_DAYS},
+        session::{ClientInfo, SessionService},
+        JwtService, RefreshTokenService,
+    },
     AppError,
 };
 
+// Path of the refresh token cookie: the endpoints that take it
+const REFRESH_COOKIE_PATH: &str = "/api/auth";
+
 #[derive(Debug, Deserialize)]
 pub struct LoginRequest {
     pub email: String,
@@ -26,13 +38,15 @@ pub struct LoginRequest {
 
 pub async fn login(
     cookies: Cookies,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     State(state): State<AppState>,
     body: Result<Json<LoginRequest>, JsonRejection>,
 ) -> Result<Response, AppError> {
     info!("-> HANDLER - POST /auth/login");
 
     let Json(body) = body.map_err(|_| AppError::BadRequest("Invalid JSON".to_string()))?;
-    let email = body.email.trim();
+    let email = &email::normalize(&body.email);
 
     info!("Login attempt for email: {}", email);
 
@@ -50,7 +64,16 @@ pub async fn login(
         .map_err(|e| AppError::UnhandledError(e.to_string()))?;
 
     if is_valid {
-        handle_successful_login(&cookies, &user, email, body.use_cookie).await
+        // Checked after the password, so it doesn't tell whether an address is verified
+        if require_verified_email() && !user.is_verified {
+            info!("Login attempt with unverified email: {}", email);
+            return Err(AppError::Forbidden(
+                "Email address is not verified".to_string(),
+            ));
+        }
+
+        let client = ClientInfo::new(&headers, Some(peer));
+        handle_successful_login(&state, &cookies, &user, email, body.use_cookie, &client).await
     } else {
         handle_failed_login(&cookies, email).await
     }
@@ -60,7 +83,7 @@ async fn get_user_by_email(state: &AppState, email: &str) -> Result<User, AppErr
     sqlx::query_as!(
         User,
         r#"
-        SELECT id, username, email, password_hash, first_name, last_name, 
+        SELECT id, code, username, email, password_hash, first_name, last_name, 
                is_active, is_verified, last_login_at, created_at, created_by, updated_at, updated_by
         FROM users
         WHERE email = $1
@@ -80,36 +103,75 @@ async fn get_user_by_email(state: &AppState, email: &str) -> Result<User, AppErr
 }
 
 async fn handle_successful_login(
+    state: &AppState,
     cookies: &Cookies,
     user: &User,
     email: &str,
     use_cookie: Option<bool>,
+    client: &ClientInfo,
 ) -> Result<Response, AppError> {
     info!("Password is valid for user: {}", email);
 
-    // Generate JWT token
-    let jwt_token = JwtService::generate_token(user.id, user.username.clone(), user.email.clone())?;
+    // Every login starts a new session, with its own refresh token family
+    let refresh_token = RefreshTokenService::issue(&state.db, user.id, client).await?;
 
-    if use_cookie.unwrap_or(true) {
-        // Default to using cookies
-        // Set JWT as HTTP-only cookie for security
-        let mut cookie = Cookie::new(AUTH_TOKEN, jwt_token.clone());
-        cookie.set_http_only(true);
-        cookie.set_secure(false); // Set to true in production with HTTPS
-        cookie.set_path("/");
+    issue_tokens(
+        cookies,
+        user,
+        refresh_token,
+        use_cookie.unwrap_or(true), // Default to using cookies
+        "Login successful",
+    )
+}
 
-        // Set cookie max age to match JWT expiry (24 hours)
-        cookie.set_max_age(tower_cookies::cookie::time::Duration::hours(24));
+async fn handle_failed_login(cookies: &Cookies, email: &str) -> Result<Response, AppError> {
+    info!("Invalid password for user: {}", email);
+    remove_auth_cookies(cookies);
+    Err(AppError::LoginFailed)
+}
 
-        cookies.add(cookie);
+// Signs a new access token for the session of `refresh_token` and returns both,
+// also as HTTP-only cookies when the client uses them
+fn issue_tokens(
+    cookies: &Cookies,
+    user: &User,
+    refresh_token: IssuedRefreshToken,
+    use_cookie: bool,
+    message: &str,
+) -> Result<Response, AppError> {
+    let jwt_token = JwtService::generate_token(
+        user.id,
+        refresh_token.session_id,
+        user.username.clone(),
+        user.email.clone(),
+    )?;
+    let refresh_token = refresh_token.token;
+
+    if use_cookie {
+        cookies.add(auth_cookie(
+            AUTH_TOKEN,
+            jwt_token.clone(),
+            "/",
+            Duration::minutes(ACCESS_TOKEN_TTL_MINUTES),
+        ));
+        // Only sent to the refresh and logout endpoints
+        cookies.add(auth_cookie(
+            REFRESH_TOKEN,
+            refresh_token.clone(),
+            REFRESH_COOKIE_PATH,
+            Duration::days(REFRESH_TOKEN_TTL_DAYS),
+        ));
     }
 
     let response_body = json!({
         "success": true,
-        "message": "Login successful",
-        "token": jwt_token, // Also return token in response for API clients
+        "message": message,
+        "token": jwt_token, // Also return tokens in response for API clients
+        "refresh_token": refresh_token,
+        "expires_in": ACCESS_TOKEN_TTL_MINUTES * 60,
         "user": {
             "id": user.id,
+            "code": user.code,
             "username": user.username,
             "email": user.email,
             "first_name": user.first_name,
@@ -121,16 +183,73 @@ async fn handle_successful_login(
     Ok((StatusCode::OK, Json(response_body)).into_response())
 }
 
-async fn handle_failed_login(cookies: &Cookies, email: &str) -> Result<Response, AppError> {
-    info!("Invalid password for user: {}", email);
+fn auth_cookie(
+    name: &'static str,
+    value: String,
+    path: &'static str,
+    max_age: Duration,
+) -> Cookie<'static> {
+    let mut cookie = Cookie::new(name, value);
+    cookie.set_http_only(true);
+    cookie.set_secure(false); // Set to true in production with HTTPS
+    cookie.set_path(path);
+    cookie.set_max_age(max_age);
+    cookie
+}
+
+pub fn remove_auth_cookies(cookies: &Cookies) {
     cookies.remove(Cookie::from(AUTH_TOKEN));
-    Err(AppError::LoginFailed)
+    cookies.remove(
+        Cookie::build(REFRESH_TOKEN)
+            .path(REFRESH_COOKIE_PATH)
+            .build(),
+    );
 }
 
-pub async fn logout(cookies: Cookies) -> Result<Response, AppError> {
+/// Body of refresh and logout for clients that don't use cookies.
+#[derive(Debug, Deserialize)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: Option<String>,
+}
+
+// Refresh token from the body, else from the cookie; `true` when it came from the cookie
+fn get_refresh_token(
+    cookies: &Cookies,
+    body: Result<Json<RefreshTokenRequest>, JsonRejection>,
+) -> Option<(String, bool)> {
+    match body.ok().and_then(|Json(body)| body.refresh_token) {
+        Some(token) => Some((token, false)),
+        None => cookies
+            .get(REFRESH_TOKEN)
+            .map(|cookie| (cookie.value().to_string(), true)),
+    }
+}
+
+pub async fn logout(
+    headers: HeaderMap,
+    cookies: Cookies,
+    State(state): State<AppState>,
+    body: Result<Json<RefreshTokenRequest>, JsonRejection>,
+) -> Result<Response, AppError> {
     info!("-> HANDLER - POST /auth/logout");
 
-    cookies.remove(Cookie::from(AUTH_TOKEN));
+    let refresh_token = get_refresh_token(&cookies, body);
+    let claims = get_access_token(&headers, &cookies)
+        .and_then(|token| JwtService::validate_token(&token).ok());
+    remove_auth_cookies(&cookies);
+
+    // Nothing names a session to end
+    if refresh_token.is_none() && claims.is_none() {
+        return Err(AppError::UnAuthorized);
+    }
+
+    // Revoke server-side, so neither token outlives the cookies
+    if let Some((token, _)) = refresh_token {
+        RefreshTokenService::revoke(&state.db, &token).await?;
+    }
+    if let Some(claims) = claims {
+        SessionService::revoke(&state.db, claims.session_id()?, Some(claims.user_id()?)).await?;
+    }
 
     let response_body = json!({
         "message": "Logout successful"
@@ -139,92 +258,90 @@ pub async fn logout(cookies: Cookies) -> Result<Response, AppError> {
     Ok((StatusCode::OK, Json(response_body)).into_response())
 }
 
+// Access token from the Authorization header first, then from the cookie
+fn get_access_token(headers: &HeaderMap, cookies: &Cookies) -> Option<String> {
+    match headers.get(header::AUTHORIZATION) {
+        Some(value) => value
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")
+            .map(str::to_string),
+        None => cookies
+            .get(AUTH_TOKEN)
+            .map(|cookie| cookie.value().to_string()),
+    }
+}
+
 pub async fn refresh_token(
     cookies: Cookies,
     State(state): State<AppState>,
+    body: Result<Json<RefreshTokenRequest>, JsonRejection>,
 ) -> Result<Response, AppError> {
     info!("-> HANDLER - POST /auth/refresh");
 
-    // Get current token from cookie
-    let current_token = cookies
-        .get(AUTH_TOKEN)
-        .map(|cookie| cookie.value().to_string())
-        .ok_or(AppError::UnAuthorized)?;
-
-    // Validate current token
-    let claims = JwtService::validate_token(&current_token)?;
-    let _user_id = claims.user_id()?; // Validate user_id format
+    let (current_token, use_cookie) =
+        get_refresh_token(&cookies, body).ok_or(AppError::UnAuthorized)?;
+
+    // Rotate: the presented token is spent, reusing it later revokes the family
+    let new_refresh_token = match RefreshTokenService::rotate(&state.db, &current_token).await {
+        Ok(rotated) => rotated,
+        Err(e) => {
+            if use_cookie {
+                remove_auth_cookies(&cookies);
+            }
+            return Err(e);
+        }
+    };
 
     // Get fresh user data from database
-    let user = get_user_by_email(&state, &claims.email).await?;
+    let user = get_user_by_id(&state, new_refresh_token.user_id).await?;
 
     // Check if user is still active
     if !user.is_active {
-        info!("Refresh attempt for inactive user: {}", claims.email);
+        info!("Refresh attempt for inactive user: {}", user.email);
+        RefreshTokenService::revoke(&state.db, &new_refresh_token.token).await?;
         return Err(AppError::UnAuthorized);
     }
 
-    // Generate new JWT token
-    let new_jwt_token =
-        JwtService::generate_token(user.id, user.username.clone(), user.email.clone())?;
-
-    // Set new JWT as HTTP-only cookie
-    let mut cookie = Cookie::new(AUTH_TOKEN, new_jwt_token.clone());
-    cookie.set_http_only(true);
-    cookie.set_secure(false); // Set to true in production with HTTPS
-    cookie.set_path("/");
-
-    // Set cookie max age to match JWT expiry (24 hours)
-    cookie.set_max_age(tower_cookies::cookie::time::Duration::hours(24));
-
-    cookies.add(cookie);
+    issue_tokens(
+        &cookies,
+        &user,
+        new_refresh_token,
+        use_cookie,
+        "Token refreshed successfully",
+    )
+}
 
-    let response_body = json!({
-        "success": true,
-        "message": "Token refreshed successfully",
-        "token": new_jwt_token,
-        "user": {
-            "id": user.id,
-            "username": user.username,
-            "email": user.email,
-            "first_name": user.first_name,
-            "last_name": user.last_name,
-            "is_verified": user.is_verified
-        }
-    });
+// GET /.well-known/jwks.json
+pub async fn jwks() -> Response {
+    info!("-> HANDLER - GET /.well-known/jwks.json");
 
-    Ok((StatusCode::OK, Json(response_body)).into_response())
+    // Cached briefly, so a key added for rotation is picked up soon
+    (
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(JwtService::jwks()),
+    )
+        .into_response()
 }
 
 pub async fn me(
-    headers: axum::http::HeaderMap,
+    headers: HeaderMap,
     cookies: Cookies,
     State(state): State<AppState>,
 ) -> Result<Response, AppError> {
     info!("-> HANDLER - GET /auth/me");
 
-    // Try to get token from Authorization header first, then from cookie
-    let token = if let Some(auth_header) = headers.get("authorization") {
-        let auth_str = auth_header.to_str().map_err(|_| AppError::UnAuthorized)?;
-
-        if auth_str.starts_with("Bearer ") {
-            Some(auth_str.strip_prefix("Bearer ").unwrap().to_string())
-        } else {
-            None
-        }
-    } else if let Some(cookie) = cookies.get(AUTH_TOKEN) {
-        Some(cookie.value().to_string())
-    } else {
-        None
-    };
-
-    let token = token.ok_or(AppError::UnAuthorized)?;
+    let token = get_access_token(&headers, &cookies).ok_or(AppError::UnAuthorized)?;
 
     // Validate JWT token
     let claims = JwtService::validate_token(&token)?;
 
     // Get user from database to check if still active
     let user_id = claims.user_id()?;
+    if !SessionService::touch(&state.db, user_id, claims.session_id()?).await? {
+        info!("Session {} was revoked", claims.sid);
+        return Err(AppError::UnAuthorized);
+    }
     let user = get_user_by_id(&state, user_id).await?;
 
     // Check if user is still active
@@ -238,6 +355,7 @@ pub async fn me(
         "status": "success",
         "data": {
             "id": user.id,
+            "code": user.code,
             "username": user.username,
             "email": user.email,
             "first_name": user.first_name,
@@ -257,7 +375,7 @@ async fn get_user_by_id(state: &AppState, user_id: uuid::Uuid) -> Result<User, A
     sqlx::query_as!(
         User,
         r#"
-        SELECT id, username, email, password_hash, first_name, last_name, 
+        SELECT id, code, username, email, password_hash, first_name, last_name, 
                is_active, is_verified, last_login_at, created_at, created_by, updated_at, updated_by
         FROM users 
         WHERE id = $1
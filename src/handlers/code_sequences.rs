@@ -0,0 +1,79 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::Deserialize;
+
+use crate::{
+    handlers::{contacts::ContactResource, users::UserResource},
+    models::{AppState, AuthenticatedUser},
+    utils::{
+        code_sequence::{CodeSequence, SequenceStatus},
+        Resource,
+    },
+    AppError,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct ResetSequenceRequest {
+    /// Year of a yearly sequence; defaults to the current one
+    pub period: Option<String>,
+    /// Number the next code follows, 0 to start over at 1
+    #[serde(default)]
+    pub last_value: i64,
+}
+
+// Tables with generated codes and their numbering
+fn sequences() -> Vec<(&'static str, CodeSequence)> {
+    [
+        (UserResource::TABLE, UserResource::CODE_SEQUENCE),
+        (ContactResource::TABLE, ContactResource::CODE_SEQUENCE),
+    ]
+    .into_iter()
+    .filter_map(|(table, sequence)| Some((table, sequence?)))
+    .collect()
+}
+
+fn sequence(entity: &str) -> Result<CodeSequence, AppError> {
+    sequences()
+        .into_iter()
+        .find(|(table, _)| *table == entity)
+        .map(|(_, sequence)| sequence)
+        .ok_or_else(|| AppError::BadRequest(format!("{} has no code sequence", entity)))
+}
+
+// GET /api/v1/code-sequences
+pub async fn list_code_sequences(
+    state: State<AppState>,
+) -> Result<Json<Vec<SequenceStatus>>, AppError> {
+    let mut statuses = Vec::new();
+    for (table, sequence) in sequences() {
+        statuses.extend(sequence.status(&state.db, table).await?);
+    }
+    Ok(Json(statuses))
+}
+
+// PUT /api/v1/code-sequences/:entity/:prefix
+pub async fn reset_code_sequence(
+    Path((entity, prefix)): Path<(String, String)>,
+    state: State<AppState>,
+    auth: Extension<AuthenticatedUser>,
+    Json(request): Json<ResetSequenceRequest>,
+) -> Result<StatusCode, AppError> {
+    let sequence = sequence(&entity)?;
+    let period = request.period.unwrap_or_else(|| sequence.current_period());
+
+    sequence
+        .reset(
+            &state.db,
+            &entity,
+            &prefix,
+            &period,
+            request.last_value,
+            auth.user_id(),
+        )
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
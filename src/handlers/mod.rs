@@ -1,4 +1,8 @@
 pub mod auth;
+pub mod code_sequences;
 pub mod contacts;
 pub mod health;
+pub mod registration;
+pub mod resource;
+pub mod sessions;
 pub mod users;
@@ -0,0 +1,274 @@
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Map};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    handlers::users::UserResource,
+    models::{AppState, User},
+    utils::{
+        email, jwt::VERIFICATION_TOKEN_TTL_HOURS, mailer::Email, JwtService, Resource,
+        ResourceFields,
+    },
+    AppError, FieldError,
+};
+
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterRequest {
+    pub username: String,
+    pub email: String,
+    pub password: String,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResendVerificationRequest {
+    pub email: String,
+}
+
+/// Whether login refuses users whose email isn't verified (`REQUIRE_VERIFIED_EMAIL=true`).
+pub fn require_verified_email() -> bool {
+    std::env::var("REQUIRE_VERIFIED_EMAIL").is_ok_and(|value| value == "true")
+}
+
+// POST /api/auth/register
+pub async fn register(
+    State(state): State<AppState>,
+    body: Result<Json<RegisterRequest>, JsonRejection>,
+) -> Result<Response, AppError> {
+    info!("-> HANDLER - POST /auth/register");
+
+    let Json(body) = body.map_err(|_| AppError::BadRequest("Invalid JSON".to_string()))?;
+    let username = body.username.trim().to_string();
+    let email = email::normalize(&body.email);
+
+    let mut errors = Vec::new();
+    if username.is_empty() {
+        errors.push(field_error("username", "is required"));
+    }
+    if email.parse::<lettre::Address>().is_err() {
+        errors.push(field_error("email", "is not a valid email address"));
+    }
+    if body.password.chars().count() < MIN_PASSWORD_LENGTH {
+        errors.push(field_error(
+            "password",
+            &format!("must be at least {} characters", MIN_PASSWORD_LENGTH),
+        ));
+    }
+    if !errors.is_empty() {
+        return Err(AppError::InvalidFields(errors));
+    }
+
+    // Hashing takes a while, so it runs on the blocking pool
+    let password = body.password.clone();
+    let password_hash =
+        tokio::task::spawn_blocking(move || bcrypt::hash(password, bcrypt::DEFAULT_COST))
+            .await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?
+            .map_err(|e| {
+                AppError::InternalServerError(format!("Password hashing failed: {}", e))
+            })?;
+
+    let mut tx = state
+        .db
+        .begin()
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+    // Numbered like users created by admins; a self-registered user is their own creator
+    let code = match UserResource::CODE_SEQUENCE {
+        Some(sequence) => Some(
+            sequence
+                .next(&mut tx, User::TABLE, &Map::new())
+                .await
+                .map_err(|e| AppError::DatabaseError(e.to_string()))?,
+        ),
+        None => None,
+    };
+    let user_id = Uuid::new_v4();
+    let inserted = sqlx::query(
+        "INSERT INTO users (id, code, username, email, password_hash, first_name, last_name, \
+         is_active, is_verified, created_by, updated_by) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, true, false, $1, $1)",
+    )
+    .bind(user_id)
+    .bind(code)
+    .bind(&username)
+    .bind(&email)
+    .bind(password_hash)
+    .bind(&body.first_name)
+    .bind(&body.last_name)
+    .execute(&mut *tx)
+    .await;
+
+    // A taken address gets the same answer as a new one, so registrations can't be
+    // probed; its owner is told by email instead
+    let sent = match inserted {
+        Ok(_) => {
+            tx.commit()
+                .await
+                .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            // The account exists either way; a failed email can be resent
+            send_verification(&state, user_id, &email).await
+        }
+        Err(e) if constraint(&e) == Some("users_email_key") => {
+            drop(tx);
+            info!("Registration with an already registered email: {}", email);
+            send_registration_notice(&state, &email).await
+        }
+        Err(e) => return Err(register_error(e)),
+    };
+    if let Err(e) = sent {
+        tracing::error!("Failed to send registration email to {}: {:?}", email, e);
+    }
+
+    let response_body = json!({
+        "success": true,
+        "message": "Registration received, check your email to continue"
+    });
+
+    Ok((StatusCode::CREATED, Json(response_body)).into_response())
+}
+
+// POST /api/auth/verify-email
+pub async fn verify_email(
+    State(state): State<AppState>,
+    body: Result<Json<VerifyEmailRequest>, JsonRejection>,
+) -> Result<Response, AppError> {
+    info!("-> HANDLER - POST /auth/verify-email");
+
+    let Json(body) = body.map_err(|_| AppError::BadRequest("Invalid JSON".to_string()))?;
+    let claims = JwtService::validate_verification_token(&body.token)?;
+    let user_id = claims.user_id()?;
+
+    // Only the address the link was sent to: changing it needs a new link
+    let verified = sqlx::query(
+        "UPDATE users SET is_verified = true, updated_by = $1 \
+         WHERE id = $1 AND email = $2 AND is_active",
+    )
+    .bind(user_id)
+    .bind(&claims.email)
+    .execute(&state.db)
+    .await
+    .map_err(|e| AppError::DatabaseError(e.to_string()))?
+    .rows_affected();
+    if verified == 0 {
+        return Err(AppError::BadRequest(
+            "Invalid or expired verification token".to_string(),
+        ));
+    }
+
+    let response_body = json!({
+        "success": true,
+        "message": "Email verified"
+    });
+
+    Ok((StatusCode::OK, Json(response_body)).into_response())
+}
+
+// POST /api/auth/verify-email/resend
+pub async fn resend_verification(
+    State(state): State<AppState>,
+    body: Result<Json<ResendVerificationRequest>, JsonRejection>,
+) -> Result<Response, AppError> {
+    info!("-> HANDLER - POST /auth/verify-email/resend");
+
+    let Json(body) = body.map_err(|_| AppError::BadRequest("Invalid JSON".to_string()))?;
+    let email = email::normalize(&body.email);
+
+    let user: Option<(Uuid, String)> = sqlx::query_as(
+        "SELECT id, email FROM users \
+         WHERE email = $1 AND is_active AND NOT is_verified",
+    )
+    .bind(&email)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+    if let Some((user_id, email)) = user {
+        send_verification(&state, user_id, &email).await?;
+    }
+
+    // Same answer for unknown addresses, so registrations can't be probed
+    let response_body = json!({
+        "success": true,
+        "message": "If the address belongs to an unverified account, a verification email was sent"
+    });
+
+    Ok((StatusCode::OK, Json(response_body)).into_response())
+}
+
+async fn send_verification(state: &AppState, user_id: Uuid, email: &str) -> Result<(), AppError> {
+    let token = JwtService::generate_verification_token(user_id, email.to_string())?;
+    let app_url = std::env::var("APP_URL").unwrap_or_else(|_| "http://localhost:5001".to_string());
+    let link = format!(
+        "{}/verify-email?token={}",
+        app_url.trim_end_matches('/'),
+        token
+    );
+
+    state
+        .mailer
+        .send(&Email {
+            to: email.to_string(),
+            subject: "Verify your email address".to_string(),
+            body: format!(
+                "Confirm your email address by opening this link within {} hours:\n\n{}\n\n\
+                 If you didn't create an account, ignore this email.",
+                VERIFICATION_TOKEN_TTL_HOURS, link
+            ),
+        })
+        .await
+}
+
+// Sent instead of a verification link when the address already has an account
+async fn send_registration_notice(state: &AppState, email: &str) -> Result<(), AppError> {
+    let app_url = std::env::var("APP_URL").unwrap_or_else(|_| "http://localhost:5001".to_string());
+
+    state
+        .mailer
+        .send(&Email {
+            to: email.to_string(),
+            subject: "Someone tried to register with your email address".to_string(),
+            body: format!(
+                "An account with this email address already exists, so no new one was \
+                 created. If it was you, sign in at {} instead.\n\n\
+                 If it wasn't you, ignore this email; your account is unchanged.",
+                app_url.trim_end_matches('/')
+            ),
+        })
+        .await
+}
+
+fn field_error(field: &str, error: &str) -> FieldError {
+    FieldError {
+        field: field.to_string(),
+        error: error.to_string(),
+    }
+}
+
+fn constraint(e: &sqlx::Error) -> Option<&str> {
+    e.as_database_error().and_then(|db| db.constraint())
+}
+
+fn register_error(e: sqlx::Error) -> AppError {
+    match constraint(&e) {
+        Some("users_username_key") => {
+            AppError::InvalidFields(vec![field_error("username", "is already taken")])
+        }
+        _ => AppError::DatabaseError(e.to_string()),
+    }
+}
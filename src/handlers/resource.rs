@@ -0,0 +1,445 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    models::{AppState, AuthenticatedUser},
+    utils::{
+        patch::{Patch, JSON_PATCH_CONTENT_TYPE},
+        AggregateResponse, BulkDeleteRequest, BulkResponse, CrudService, PaginatedResponse,
+        QueryParams, Resource, Sparse,
+    },
+    AppError, BulkItemError,
+};
+
+// GET /api/v1/{resource}
+pub async fn list<R: Resource>(
+    query: Query<QueryParams>,
+    state: State<AppState>,
+    auth: Extension<AuthenticatedUser>,
+) -> Result<Json<PaginatedResponse<Sparse<R::Response>>>, AppError> {
+    CrudService::list::<R>(query, state, &R::base_url(), Some(auth)).await
+}
+
+// GET /api/v1/{resource}/aggregate
+pub async fn aggregate<R: Resource>(
+    query: Query<QueryParams>,
+    state: State<AppState>,
+    auth: Extension<AuthenticatedUser>,
+) -> Result<Json<AggregateResponse>, AppError> {
+    CrudService::aggregate::<R>(query, state, Some(auth)).await
+}
+
+// GET /api/v1/{resource}/:id
+pub async fn get_by_id<R: Resource>(
+    id: Path<Uuid>,
+    query: Query<QueryParams>,
+    headers: HeaderMap,
+    state: State<AppState>,
+    auth: Extension<AuthenticatedUser>,
+) -> Result<Response, AppError> {
+    CrudService::get_by_id::<R>(&query, id, &headers, state, Some(auth)).await
+}
+
+// POST /api/v1/{resource}
+pub async fn create<R: Resource>(
+    state: State<AppState>,
+    auth: Extension<AuthenticatedUser>,
+    Json(body): Json<serde_json::Value>,
+) -> Result<Json<R::Response>, AppError> {
+    CrudService::check_body(&body, R::CREATE_BODY_FIELDS)?;
+    let create_data = serde_json::from_value::<R::Create>(body)
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+    let values = create_object::<R>(create_data).await?;
+    let Json(row) = CrudService::create::<R>(values, state, auth).await?;
+
+    Ok(Json(row.into()))
+}
+
+// PUT /api/v1/{resource}/:id
+pub async fn update<R: Resource>(
+    id: Path<Uuid>,
+    headers: HeaderMap,
+    state: State<AppState>,
+    auth: Extension<AuthenticatedUser>,
+    Json(body): Json<serde_json::Value>,
+) -> Result<Response, AppError> {
+    let (etag, Json(row)) = CrudService::update::<R>(id, body, &headers, state, auth).await?;
+
+    Ok((etag, Json(R::Response::from(row))).into_response())
+}
+
+// PUT /api/v1/{resource}/by-{key}/:value
+// Creates the row with that natural key from a create body, or updates the fields
+// the body sends. Answers 201 when the row was created and 200 when it was updated.
+pub async fn upsert<R: Resource>(
+    key: &'static str,
+    Path(value): Path<String>,
+    state: State<AppState>,
+    auth: Extension<AuthenticatedUser>,
+    Json(body): Json<serde_json::Value>,
+) -> Result<Response, AppError> {
+    // The key is accepted even when creates can't set it
+    let mut accepted = R::CREATE_BODY_FIELDS.to_vec();
+    accepted.push(key);
+    CrudService::check_body(&body, &accepted)?;
+    let sent: Vec<String> = match &body {
+        serde_json::Value::Object(object) => object.keys().cloned().collect(),
+        _ => Vec::new(),
+    };
+    let create_data = serde_json::from_value::<R::Create>(body)
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+    let mut values = create_object::<R>(create_data).await?;
+    let value = R::upsert_key(key, &value);
+    match values.get(key) {
+        Some(serde_json::Value::String(existing)) if R::upsert_key(key, existing) != value => {
+            return Err(AppError::BadRequest(format!(
+                "{} in the body does not match the URL",
+                key
+            )));
+        }
+        _ => {
+            values.insert(key.to_string(), serde_json::Value::String(value));
+        }
+    }
+
+    let (created, etag, Json(row)) =
+        CrudService::upsert::<R>(key, values, &sent, state, auth).await?;
+
+    let status = if created {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+    Ok((status, etag, Json(R::Response::from(row))).into_response())
+}
+
+// PATCH /api/v1/{resource}/:id
+// A merge patch, or a JSON Patch with `Content-Type: application/json-patch+json`
+pub async fn patch<R: Resource>(
+    id: Path<Uuid>,
+    headers: HeaderMap,
+    state: State<AppState>,
+    auth: Extension<AuthenticatedUser>,
+    Json(body): Json<serde_json::Value>,
+) -> Result<Response, AppError> {
+    let is_json_patch = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with(JSON_PATCH_CONTENT_TYPE));
+    let patch = if is_json_patch {
+        Patch::Json(serde_json::from_value(body).map_err(|e| AppError::BadRequest(e.to_string()))?)
+    } else {
+        Patch::Merge(body)
+    };
+
+    let (etag, Json(row)) = CrudService::patch::<R>(id, patch, &headers, state, auth).await?;
+
+    Ok((etag, Json(R::Response::from(row))).into_response())
+}
+
+// DELETE /api/v1/{resource}/:id
+pub async fn delete<R: Resource>(
+    id: Path<Uuid>,
+    headers: HeaderMap,
+    state: State<AppState>,
+    auth: Extension<AuthenticatedUser>,
+) -> Result<Response, AppError> {
+    CrudService::delete::<R>(id, &headers, state, auth).await
+}
+
+// POST /api/v1/{resource}/:id/restore
+pub async fn restore<R: Resource>(
+    id: Path<Uuid>,
+    state: State<AppState>,
+    auth: Extension<AuthenticatedUser>,
+) -> Result<Json<R::Response>, AppError> {
+    let Json(row) = CrudService::restore::<R>(id, state, auth).await?;
+
+    Ok(Json(row.into()))
+}
+
+// DELETE /api/v1/{resource}/:id/purge
+pub async fn purge<R: Resource>(
+    id: Path<Uuid>,
+    state: State<AppState>,
+    auth: Extension<AuthenticatedUser>,
+) -> Result<Response, AppError> {
+    CrudService::purge::<R>(id, state, auth).await
+}
+
+// POST /api/v1/{resource}/bulk
+pub async fn bulk_create<R: Resource>(
+    state: State<AppState>,
+    auth: Extension<AuthenticatedUser>,
+    Json(items): Json<Vec<serde_json::Value>>,
+) -> Result<Json<BulkResponse<R::Response>>, AppError> {
+    let mut creates = Vec::with_capacity(items.len());
+    let mut errors = Vec::new();
+    for (index, item) in items.into_iter().enumerate() {
+        let fields = CrudService::body_errors(&item, R::CREATE_BODY_FIELDS);
+        if !fields.is_empty() {
+            errors.push(BulkItemError {
+                index,
+                id: None,
+                error: "Invalid fields".to_string(),
+                fields,
+            });
+            continue;
+        }
+        match serde_json::from_value::<R::Create>(item) {
+            Ok(data) => creates.push(data),
+            Err(e) => errors.push(BulkItemError {
+                index,
+                id: None,
+                error: e.to_string(),
+                fields: Vec::new(),
+            }),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(AppError::BulkFailed(errors));
+    }
+
+    let values = create_objects::<R>(creates).await?;
+    let Json(response) = CrudService::bulk_create::<R>(values, state, auth).await?;
+
+    Ok(Json(into_responses::<R>(response)))
+}
+
+// PATCH /api/v1/{resource}/bulk
+pub async fn bulk_update<R: Resource>(
+    state: State<AppState>,
+    auth: Extension<AuthenticatedUser>,
+    Json(items): Json<Vec<serde_json::Value>>,
+) -> Result<Json<BulkResponse<R::Response>>, AppError> {
+    let mut updates = Vec::with_capacity(items.len());
+    let mut errors = Vec::new();
+    for (index, item) in items.into_iter().enumerate() {
+        match bulk_update_item(item) {
+            Ok(update) => updates.push(update),
+            Err(error) => errors.push(BulkItemError {
+                index,
+                id: None,
+                error,
+                fields: Vec::new(),
+            }),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(AppError::BulkFailed(errors));
+    }
+
+    let Json(response) = CrudService::bulk_update::<R>(updates, state, auth).await?;
+
+    Ok(Json(into_responses::<R>(response)))
+}
+
+// DELETE /api/v1/{resource}/bulk
+pub async fn bulk_delete<R: Resource>(
+    state: State<AppState>,
+    auth: Extension<AuthenticatedUser>,
+    Json(request): Json<BulkDeleteRequest>,
+) -> Result<Json<BulkResponse<Uuid>>, AppError> {
+    CrudService::bulk_delete::<R>(request.ids, state, auth).await
+}
+
+// Column values of a create request, as the object `CrudService` inserts
+async fn create_object<R: Resource>(
+    data: R::Create,
+) -> Result<serde_json::Map<String, serde_json::Value>, AppError> {
+    let mut values = create_objects::<R>(vec![data]).await?;
+    Ok(values.remove(0))
+}
+
+// `create_values` can be expensive, e.g. hashing a password, so it runs on the
+// blocking pool instead of stalling the async workers
+async fn create_objects<R: Resource>(
+    items: Vec<R::Create>,
+) -> Result<Vec<serde_json::Map<String, serde_json::Value>>, AppError> {
+    tokio::task::spawn_blocking(move || {
+        items
+            .into_iter()
+            .map(|data| match R::create_values(data)? {
+                serde_json::Value::Object(values) => Ok(values),
+                _ => Err(AppError::SerializationError(
+                    "create values must be an object".to_string(),
+                )),
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(e.to_string()))?
+}
+
+// Split a bulk update item into its `id` and the fields it writes, merge patch style
+fn bulk_update_item(
+    item: serde_json::Value,
+) -> Result<(Uuid, serde_json::Map<String, serde_json::Value>), String> {
+    let serde_json::Value::Object(mut item) = item else {
+        return Err("expected an object".to_string());
+    };
+    let id = item
+        .remove("id")
+        .ok_or_else(|| "missing field `id`".to_string())
+        .and_then(|id| serde_json::from_value::<Uuid>(id).map_err(|e| e.to_string()))?;
+
+    // Merge patch semantics: only the fields present are written
+    Ok((id, item))
+}
+
+fn into_responses<R: Resource>(response: BulkResponse<R::Row>) -> BulkResponse<R::Response> {
+    BulkResponse {
+        count: response.count,
+        results: response
+            .results
+            .into_iter()
+            .map(R::Response::from)
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        handlers::{contacts::ContactResource, users::UserResource},
+        utils::{column_types::ColumnTypeCache, mailer},
+    };
+    use serde_json::{json, Value};
+    use std::collections::HashSet;
+
+    // Never connects: these requests are turned away before reaching the database
+    fn state() -> State<AppState> {
+        State(AppState {
+            db: sqlx::postgres::PgPoolOptions::new()
+                .connect_lazy("postgres://localhost/unused")
+                .unwrap(),
+            column_types: ColumnTypeCache::default(),
+            mailer: mailer::from_env().unwrap(),
+        })
+    }
+
+    fn auth() -> Extension<AuthenticatedUser> {
+        Extension(AuthenticatedUser::new(
+            Uuid::nil(),
+            Uuid::nil(),
+            HashSet::new(),
+        ))
+    }
+
+    async fn rejection(result: Result<impl IntoResponse, AppError>) -> (StatusCode, Value) {
+        let response = match result {
+            Ok(_) => panic!("expected the request to be rejected"),
+            Err(e) => e.into_response(),
+        };
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_create_rejects_read_only_and_unknown_fields() {
+        let body = json!({"id": Uuid::nil(), "code": "C-1", "first_name": "Jo", "nickname": "J"});
+        let (status, body) =
+            rejection(create::<ContactResource>(state(), auth(), Json(body)).await).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body["details"],
+            json!([
+                {"field": "code", "error": "not a writable field"},
+                {"field": "id", "error": "managed by the server"},
+                {"field": "nickname", "error": "not a writable field"}
+            ])
+        );
+
+        // A user is created from a password, never from its hash
+        let body = json!({
+            "username": "jo",
+            "email": "jo@example.com",
+            "password": "password123",
+            "password_hash": "x",
+            "is_verified": true
+        });
+        let (status, body) =
+            rejection(create::<UserResource>(state(), auth(), Json(body)).await).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let fields: Vec<&str> = body["details"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|error| error["field"].as_str().unwrap())
+            .collect();
+        assert_eq!(fields, ["is_verified", "password_hash"]);
+    }
+
+    #[tokio::test]
+    async fn test_upsert_compares_keys_in_stored_form() {
+        assert_eq!(
+            UserResource::upsert_key("email", " Jo@Example.com"),
+            "jo@example.com"
+        );
+        assert_eq!(UserResource::upsert_key("code", "U-00001"), "U-00001");
+        assert_eq!(ContactResource::upsert_key("code", "C-1"), "C-1");
+
+        let body = json!({
+            "username": "jo",
+            "email": "al@example.com",
+            "password": "password123"
+        });
+        let (status, body) = rejection(
+            upsert::<UserResource>(
+                "email",
+                Path("Jo@Example.com".to_string()),
+                state(),
+                auth(),
+                Json(body),
+            )
+            .await,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["details"], "email in the body does not match the URL");
+    }
+
+    #[tokio::test]
+    async fn test_put_and_bulk_create_reject_read_only_fields() {
+        let body = json!({"created_by": Uuid::nil()});
+        let (status, body) = rejection(
+            update::<ContactResource>(
+                Path(Uuid::nil()),
+                HeaderMap::new(),
+                state(),
+                auth(),
+                Json(body),
+            )
+            .await,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["details"][0]["error"], "managed by the server");
+
+        let items = vec![
+            json!({"first_name": "Jo", "last_name": "Doe"}),
+            json!({"first_name": "Al", "last_name": "Doe", "deleted_at": null}),
+        ];
+        let (status, body) =
+            rejection(bulk_create::<ContactResource>(state(), auth(), Json(items)).await).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            body["details"],
+            json!([{
+                "index": 1,
+                "error": "Invalid fields",
+                "fields": [{"field": "deleted_at", "error": "managed by the server"}]
+            }])
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tower_cookies::Cookies;
+use uuid::Uuid;
+
+use crate::{
+    handlers::auth::remove_auth_cookies,
+    models::{AppState, AuthenticatedUser},
+    utils::session::{Session, SessionService},
+    AppError,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct SessionQuery {
+    /// Another user's sessions; needs `sessions:read` or `sessions:delete`
+    pub user_id: Option<Uuid>,
+}
+
+// User whose sessions a request is about: the caller's own unless it names
+// someone else, which takes `permission`
+fn target_user(
+    auth: &AuthenticatedUser,
+    query: &SessionQuery,
+    permission: &str,
+) -> Result<Uuid, AppError> {
+    match query.user_id {
+        Some(user_id) if user_id != auth.user_id() => {
+            auth.require(permission)?;
+            Ok(user_id)
+        }
+        _ => Ok(auth.user_id()),
+    }
+}
+
+// GET /api/auth/sessions
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedUser>,
+    Query(query): Query<SessionQuery>,
+) -> Result<Json<Vec<Session>>, AppError> {
+    let user_id = target_user(&auth, &query, "sessions:read")?;
+    let sessions = SessionService::list(&state.db, user_id, auth.session_id()).await?;
+    Ok(Json(sessions))
+}
+
+// DELETE /api/auth/sessions/:id
+pub async fn revoke_session(
+    cookies: Cookies,
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    // Anyone may end their own sessions; other users' take `sessions:delete`
+    let owner = if auth.has_permission("sessions:delete") {
+        None
+    } else {
+        Some(auth.user_id())
+    };
+    if !SessionService::revoke(&state.db, id, owner).await? {
+        return Err(AppError::NotFound { id });
+    }
+
+    if id == auth.session_id() {
+        remove_auth_cookies(&cookies);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// DELETE /api/auth/sessions: log out everywhere
+pub async fn revoke_all_sessions(
+    cookies: Cookies,
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedUser>,
+    Query(query): Query<SessionQuery>,
+) -> Result<Json<Value>, AppError> {
+    let user_id = target_user(&auth, &query, "sessions:delete")?;
+    let revoked = SessionService::revoke_all(&state.db, user_id).await?;
+
+    if user_id == auth.user_id() {
+        remove_auth_cookies(&cookies);
+    }
+    Ok(Json(json!({ "revoked": revoked })))
+}
@@ -6,6 +6,7 @@ use axum::{
 };
 use dotenvy::dotenv;
 use sqlx::postgres::PgPoolOptions;
+use std::net::SocketAddr;
 use tower_cookies::CookieManagerLayer;
 // use serde_json::json;
 // use sqlx::postgres::PgPoolOptions;
@@ -15,9 +16,10 @@ use crate::{
     handlers::health,
     middlewares::{auth_resolver_middleware, logging_middleware},
     models::AppState,
+    utils::{column_types::ColumnTypeCache, jwt_keys::JwtKeys, mailer, JwtService},
 };
 
-pub use self::errors::{AppError, Result};
+pub use self::errors::{AppError, BulkItemError, FieldError, Result};
 
 mod errors;
 mod handlers;
@@ -45,8 +47,16 @@ async fn main() {
         .expect("Failed to connect to the database");
     info!("✅ Connected to database{}", db_url);
 
+    // Refuse to start without usable signing keys
+    let jwt_keys = JwtKeys::from_env().unwrap_or_else(|e| panic!("Invalid JWT keys: {}", e));
+    JwtService::init(jwt_keys);
+
     // Create app state
-    let app_state = AppState { db: db_pool };
+    let app_state = AppState {
+        db: db_pool,
+        column_types: ColumnTypeCache::default(),
+        mailer: mailer::from_env().unwrap_or_else(|e| panic!("Invalid mail settings: {}", e)),
+    };
 
     // ✅ Create separate routers for public and protected routes
     let public_routes = Router::new()
@@ -57,7 +67,11 @@ async fn main() {
 
     let protected_routes = Router::new()
         .nest("/api/v1/", routes::main::routes().await)
-        .layer(middleware::from_fn(auth_resolver_middleware::start)); // Auth only for protected routes
+        .merge(routes::auth::session_routes().await)
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            auth_resolver_middleware::start,
+        )); // Auth only for protected routes
 
     let app = Router::new()
         .merge(public_routes) // Public routes without auth
@@ -72,9 +86,13 @@ async fn main() {
         .expect("Failed to bind to address");
 
     info!("🚀 Server running on http://{}", &addr);
-    axum::serve(listener, app)
-        .await
-        .expect("Failed to start server");
+    // Peer addresses are recorded on login sessions
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .expect("Failed to start server");
 }
 
 async fn main_response_mapper(res: Response) -> Response {
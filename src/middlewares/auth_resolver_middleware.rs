@@ -1,14 +1,23 @@
-use axum::{body::Body, extract::Request, http::HeaderMap, middleware::Next, response::Response};
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+use std::collections::HashSet;
 use tower_cookies::{Cookie, Cookies};
 use tracing::info;
+use uuid::Uuid;
 
 use crate::{
-    models::AuthenticatedUser,
-    utils::{constants::AUTH_TOKEN, JwtService},
+    models::{AppState, AuthenticatedUser},
+    utils::{constants::AUTH_TOKEN, session::SessionService, JwtService},
     AppError,
 };
 
 pub async fn start(
+    State(state): State<AppState>,
     cookies: Cookies,
     mut req: Request<Body>,
     next: Next,
@@ -25,7 +34,19 @@ pub async fn start(
                 Ok(claims) => {
                     info!("Successfully validated JWT for user: {}", claims.username);
                     let user_id = claims.user_id()?;
-                    req.extensions_mut().insert(AuthenticatedUser::new(user_id));
+                    let session_id = claims.session_id()?;
+                    // Logged out, or revoked from another session
+                    if !SessionService::touch(&state.db, user_id, session_id).await? {
+                        info!("Session {} was revoked, removing cookie", session_id);
+                        cookies.remove(Cookie::build(AUTH_TOKEN).build());
+                        return Err(AppError::UnAuthorized);
+                    }
+                    let permissions = load_permissions(&state, user_id).await?;
+                    req.extensions_mut().insert(AuthenticatedUser::new(
+                        user_id,
+                        session_id,
+                        permissions,
+                    ));
                     Ok(next.run(req).await)
                 }
                 Err(e) => {
@@ -42,6 +63,23 @@ pub async fn start(
     }
 }
 
+// Permissions granted by the user's roles, read on every request so role
+// changes apply immediately
+async fn load_permissions(state: &AppState, user_id: Uuid) -> Result<HashSet<String>, AppError> {
+    let permissions: Vec<String> = sqlx::query_scalar(
+        "SELECT DISTINCT p.name FROM user_roles ur \
+         JOIN role_permissions rp ON rp.role_id = ur.role_id \
+         JOIN permissions p ON p.id = rp.permission_id \
+         WHERE ur.user_id = $1",
+    )
+    .bind(user_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+    Ok(permissions.into_iter().collect())
+}
+
 fn get_token_from_request(cookies: &Cookies, headers: &HeaderMap) -> Option<String> {
     // First try to get token from cookie
     if let Some(cookie_token) = cookies.get(AUTH_TOKEN) {
@@ -1,2 +1,3 @@
 pub mod auth_resolver_middleware;
 pub mod logging_middleware;
+pub mod permission_middleware;
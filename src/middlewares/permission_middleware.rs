@@ -0,0 +1,50 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    middleware::{self, Next},
+    response::Response,
+    routing::MethodRouter,
+    Extension,
+};
+use std::sync::Arc;
+
+use crate::{
+    models::{AppState, AuthenticatedUser},
+    utils::{permission, Resource},
+    AppError,
+};
+
+/// Guards `route` with `permission`: users without it get 403 Forbidden.
+///
+/// ```ignore
+/// .route("/contacts/customers", require("contacts:read", get(get_customers)))
+/// ```
+pub fn require(
+    permission: impl Into<Arc<str>>,
+    route: MethodRouter<AppState>,
+) -> MethodRouter<AppState> {
+    let permission: Arc<str> = permission.into();
+    route.route_layer(middleware::from_fn(
+        move |auth: Extension<AuthenticatedUser>, req: Request<Body>, next: Next| {
+            let permission = permission.clone();
+            async move {
+                auth.require(&permission)?;
+                Ok::<_, AppError>(next.run(req).await)
+            }
+        },
+    ))
+}
+
+/// Requires `<R::TABLE>:<action>`, the action following the request method
+/// (`read`, `write` or `delete`).
+pub async fn resource<R: Resource>(
+    auth: Extension<AuthenticatedUser>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    auth.require(&permission::permission(
+        R::TABLE,
+        permission::action(req.method()),
+    ))?;
+    Ok(next.run(req).await)
+}
@@ -3,6 +3,12 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use sqlx::PgPool;
+use std::{collections::HashSet, sync::Arc};
+
+use crate::{
+    utils::{column_types::ColumnTypeCache, mailer::Mailer, permission},
+    AppError,
+};
 
 pub mod user;
 pub mod contact;
@@ -13,21 +19,55 @@ pub use contact::*;
 #[derive(Clone)]
 pub struct AppState {
     pub db: PgPool,
+    /// Column types of written tables, for type-aware binding in `CrudService`
+    pub column_types: ColumnTypeCache,
+    /// Delivers account emails, e.g. verification links
+    pub mailer: Arc<dyn Mailer>,
 }
 
+/// The user a request is made by, with the permissions their roles grant.
 #[derive(Clone)]
 pub struct AuthenticatedUser {
     user_id: Uuid,
+    session_id: Uuid,
+    permissions: Arc<HashSet<String>>,
 }
 
 impl AuthenticatedUser {
-    pub fn new(user_id: Uuid) -> Self {
-        Self { user_id }
+    pub fn new(user_id: Uuid, session_id: Uuid, permissions: HashSet<String>) -> Self {
+        Self {
+            user_id,
+            session_id,
+            permissions: Arc::new(permissions),
+        }
     }
 
     pub fn user_id(&self) -> Uuid {
         self.user_id
     }
+
+    /// Session the request's access token belongs to.
+    pub fn session_id(&self) -> Uuid {
+        self.session_id
+    }
+
+    pub fn has_permission(&self, required: &str) -> bool {
+        self.permissions
+            .iter()
+            .any(|granted| permission::grants(granted, required))
+    }
+
+    /// `Forbidden` unless the user has `required`.
+    pub fn require(&self, required: &str) -> Result<(), AppError> {
+        if self.has_permission(required) {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden(format!(
+                "Missing permission {}",
+                required
+            )))
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
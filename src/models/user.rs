@@ -1,46 +1,80 @@
 use chrono::{DateTime, Utc};
+use resource_derive::Resource;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use super::ContactSummary;
+
+// Missing columns fall back to defaults so sparse fieldsets still deserialize.
+// The derive generates the column whitelists and the update request DTO; creating a
+// user takes a password instead of its hash, so `CreateUserRequest` is written by hand.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Resource)]
+#[serde(default)]
+#[resource(table = "users", update = UpdateUserRequest)]
 pub struct User {
     pub id: Uuid,
+    // Assigned from `UserResource::CODE_SEQUENCE` on insert
+    #[resource(searchable, filterable, sortable)]
+    pub code: String,
+    #[resource(searchable, sortable, update)]
     pub username: String,
+    #[resource(searchable, sortable, update)]
     pub email: String,
+    #[resource(hidden)]
     pub password_hash: String,
+    #[resource(searchable, sortable, update)]
     pub first_name: Option<String>,
+    #[resource(searchable, sortable, update)]
     pub last_name: Option<String>,
+    #[resource(filterable, update)]
     pub is_active: bool,
+    #[resource(filterable, update)]
     pub is_verified: bool,
+    #[resource(filterable)]
     pub last_login_at: Option<DateTime<Utc>>,
+    #[resource(filterable, sortable)]
     pub created_at: DateTime<Utc>,
+    #[resource(hidden)]
     pub created_by: Option<Uuid>,
+    #[resource(filterable, sortable)]
     pub updated_at: DateTime<Utc>,
+    #[resource(hidden)]
     pub updated_by: Option<Uuid>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct CreateUserRequest {
+/// List row: a user plus the relations requested with `include=`.
+// Kept apart from `User`, which is also loaded with `query_as!`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct UserWithIncludes {
+    #[serde(flatten)]
+    pub user: User,
+    pub created_contacts: Option<Vec<ContactSummary>>,
+}
+
+/// Compact user nested into other resources through `include=`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserSummary {
+    pub id: Uuid,
     pub username: String,
-    pub email: String,
-    pub password: String,
     pub first_name: Option<String>,
     pub last_name: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct UpdateUserRequest {
-    pub username: Option<String>,
-    pub email: Option<String>,
+#[derive(Debug, Deserialize)]
+pub struct CreateUserRequest {
+    pub username: String,
+    pub email: String,
+    pub password: String,
     pub first_name: Option<String>,
     pub last_name: Option<String>,
-    pub is_active: Option<bool>,
-    pub is_verified: Option<bool>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct UserResponse {
     pub id: Uuid,
+    pub code: String,
     pub username: String,
     pub email: String,
     pub first_name: Option<String>,
@@ -50,12 +84,15 @@ pub struct UserResponse {
     pub last_login_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_contacts: Option<Vec<ContactSummary>>,
 }
 
 impl From<User> for UserResponse {
     fn from(user: User) -> Self {
         Self {
             id: user.id,
+            code: user.code,
             username: user.username,
             email: user.email,
             first_name: user.first_name,
@@ -65,6 +102,16 @@ impl From<User> for UserResponse {
             last_login_at: user.last_login_at,
             created_at: user.created_at,
             updated_at: user.updated_at,
+            created_contacts: None,
+        }
+    }
+}
+
+impl From<UserWithIncludes> for UserResponse {
+    fn from(row: UserWithIncludes) -> Self {
+        Self {
+            created_contacts: row.created_contacts,
+            ..Self::from(row.user)
         }
     }
 }
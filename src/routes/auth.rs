@@ -1,10 +1,14 @@
 use axum::{
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
 };
 
 use crate::{
-    handlers::auth::{login, logout, me, refresh_token},
+    handlers::{
+        auth::{jwks, login, logout, me, refresh_token},
+        registration::{register, resend_verification, verify_email},
+        sessions::{list_sessions, revoke_all_sessions, revoke_session},
+    },
     models::AppState,
 };
 
@@ -14,4 +18,18 @@ pub async fn routes() -> Router<AppState> {
         .route("/api/auth/me", get(me))
         .route("/api/auth/logout", post(logout))
         .route("/api/auth/refresh", post(refresh_token))
+        .route("/api/auth/register", post(register))
+        .route("/api/auth/verify-email", post(verify_email))
+        .route("/api/auth/verify-email/resend", post(resend_verification))
+        .route("/.well-known/jwks.json", get(jwks))
+}
+
+/// Session management; mounted behind the auth middleware.
+pub async fn session_routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/api/auth/sessions",
+            get(list_sessions).delete(revoke_all_sessions),
+        )
+        .route("/api/auth/sessions/{id}", delete(revoke_session))
 }
@@ -1,35 +1,43 @@
-use axum::{routing::get, Router};
+use axum::{
+    routing::{get, put},
+    Router,
+};
 
 use crate::{
     handlers::{
-        contacts::{
-            create_contact, delete_contact, get_contact_by_id, get_contacts, get_customers,
-            get_suppliers, update_contact,
-        },
-        users::{create_user, delete_user, get_all_users, get_user_by_id, get_users, update_user},
+        code_sequences::{list_code_sequences, reset_code_sequence},
+        contacts::{get_customers, get_suppliers, ContactResource},
+        users::{get_all_users, UserResource},
     },
+    middlewares::permission_middleware::require,
     models::AppState,
+    routes::resource,
 };
 
 pub async fn routes() -> Router<AppState> {
     Router::new()
         // Users routes
         .route("/hi", get(|| async { "🚀 Hello, user login!" }))
-        .route("/users", get(get_users).post(create_user))
+        .merge(resource::router::<UserResource>())
+        // Contacts routes
+        .merge(resource::router::<ContactResource>())
         .route(
-            "/users/{id}",
-            get(get_user_by_id).put(update_user).delete(delete_user),
+            "/contacts/customers",
+            require("contacts:read", get(get_customers)),
+        )
+        .route(
+            "/contacts/suppliers",
+            require("contacts:read", get(get_suppliers)),
+        )
+        // Document-code numbering (admin)
+        .route(
+            "/code-sequences",
+            require("code_sequences:read", get(list_code_sequences)),
         )
-        // Contacts routes
-        .route("/contacts", get(get_contacts).post(create_contact))
         .route(
-            "/contacts/{id}",
-            get(get_contact_by_id)
-                .put(update_contact)
-                .delete(delete_contact),
+            "/code-sequences/{entity}/{prefix}",
+            require("code_sequences:write", put(reset_code_sequence)),
         )
-        .route("/contacts/customers", get(get_customers))
-        .route("/contacts/suppliers", get(get_suppliers))
         // Legacy route for backward compatibility
-        .route("/hello", get(get_all_users))
+        .route("/hello", require("users:read", get(get_all_users)))
 }
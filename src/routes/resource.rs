@@ -0,0 +1,69 @@
+use axum::{
+    extract::{Path, State},
+    middleware,
+    routing::{self, get, post, put},
+    Extension, Json, Router,
+};
+
+use crate::{
+    handlers::resource::{
+        aggregate, bulk_create, bulk_delete, bulk_update, create, delete, get_by_id, list, patch,
+        purge, restore, update, upsert,
+    },
+    middlewares::permission_middleware::{self, require},
+    models::{AppState, AuthenticatedUser},
+    utils::{permission::permission, Resource},
+};
+
+/// Mounts the standard CRUD routes for `R` at `R::PATH`:
+/// list/create, aggregate, bulk create/update/delete and get/update/patch/delete by id,
+/// an upsert at `by-{key}/{value}` for each of `R::UPSERT_KEYS`, plus restore and
+/// purge for soft-deleting resources.
+///
+/// Routes require `<R::TABLE>:read`, `:write` or `:delete` by request method;
+/// purge requires `<R::TABLE>:purge`.
+pub fn router<R: Resource>() -> Router<AppState> {
+    let mut router = Router::new()
+        .route(R::PATH, get(list::<R>).post(create::<R>))
+        .route(&format!("{}/aggregate", R::PATH), get(aggregate::<R>))
+        .route(
+            &format!("{}/bulk", R::PATH),
+            post(bulk_create::<R>)
+                .patch(bulk_update::<R>)
+                .delete(bulk_delete::<R>),
+        )
+        .route(
+            &format!("{}/{{id}}", R::PATH),
+            get(get_by_id::<R>)
+                .put(update::<R>)
+                .patch(patch::<R>)
+                .delete(delete::<R>),
+        );
+
+    for &key in R::UPSERT_KEYS {
+        router = router.route(
+            &format!("{}/by-{}/{{value}}", R::PATH, key),
+            put(
+                move |value: Path<String>,
+                      state: State<AppState>,
+                      auth: Extension<AuthenticatedUser>,
+                      body: Json<serde_json::Value>| {
+                    upsert::<R>(key, value, state, auth, body)
+                },
+            ),
+        );
+    }
+
+    if R::SOFT_DELETE {
+        router = router.route(&format!("{}/{{id}}/restore", R::PATH), post(restore::<R>));
+    }
+    router = router.route_layer(middleware::from_fn(permission_middleware::resource::<R>));
+
+    if R::SOFT_DELETE {
+        router = router.route(
+            &format!("{}/{{id}}/purge", R::PATH),
+            require(permission(R::TABLE, "purge"), routing::delete(purge::<R>)),
+        );
+    }
+    router
+}
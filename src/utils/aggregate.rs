@@ -0,0 +1,150 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Units accepted for `date_trunc` buckets in `group_by`.
+const DATE_TRUNC_UNITS: &[&str] = &["hour", "day", "week", "month", "quarter", "year"];
+
+/// One `group_by` entry: a column, optionally bucketed with `date_trunc`,
+/// e.g. `country` or `created_at:month`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupBy {
+    pub field: String,
+    pub bucket: Option<String>,
+}
+
+impl GroupBy {
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (field, bucket) = match spec.trim().split_once(':') {
+            Some((field, bucket)) if DATE_TRUNC_UNITS.contains(&bucket) => {
+                (field, Some(bucket.to_string()))
+            }
+            Some(_) => return None,
+            None => (spec.trim(), None),
+        };
+        if field.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            field: field.to_string(),
+            bucket,
+        })
+    }
+
+    /// Column name without its table, if qualified
+    pub fn column_name(&self) -> &str {
+        self.field.rsplit('.').next().unwrap_or(&self.field)
+    }
+
+    /// Result column name, e.g. `country` or `created_at_month`.
+    pub fn alias(&self) -> String {
+        match &self.bucket {
+            Some(bucket) => format!("{}_{}", self.column_name(), bucket),
+            None => self.column_name().to_string(),
+        }
+    }
+
+    pub fn to_sql(&self) -> String {
+        match &self.bucket {
+            Some(bucket) => format!("date_trunc('{}', {})", bucket, self.field),
+            None => self.field.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateFunction {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Count => "count",
+            Self::Sum => "sum",
+            Self::Avg => "avg",
+            Self::Min => "min",
+            Self::Max => "max",
+        }
+    }
+
+    /// Whether the function only takes numeric columns
+    pub fn is_numeric(self) -> bool {
+        matches!(self, Self::Sum | Self::Avg)
+    }
+}
+
+/// One `agg` entry: `count`, `count:field` (non-null values) or
+/// `sum|avg|min|max:field`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregate {
+    pub function: AggregateFunction,
+    pub field: Option<String>,
+}
+
+impl Aggregate {
+    pub fn count() -> Self {
+        Self {
+            function: AggregateFunction::Count,
+            field: None,
+        }
+    }
+
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (name, field) = match spec.trim().split_once(':') {
+            Some((name, field)) if !field.is_empty() => (name, Some(field.to_string())),
+            Some(_) => return None,
+            None => (spec.trim(), None),
+        };
+
+        let function = match name {
+            "count" => AggregateFunction::Count,
+            "sum" => AggregateFunction::Sum,
+            "avg" => AggregateFunction::Avg,
+            "min" => AggregateFunction::Min,
+            "max" => AggregateFunction::Max,
+            _ => return None,
+        };
+        // Only COUNT works without a column
+        if field.is_none() && function != AggregateFunction::Count {
+            return None;
+        }
+
+        Some(Self { function, field })
+    }
+
+    /// Result column name, e.g. `count` or `sum_amount`; a qualified field
+    /// contributes only its column name.
+    pub fn alias(&self) -> String {
+        let name = self.function.name();
+        match &self.field {
+            Some(field) => format!("{}_{}", name, field.rsplit('.').next().unwrap_or(field)),
+            None => name.to_string(),
+        }
+    }
+
+    pub fn to_sql(&self) -> String {
+        let field = self.field.as_deref().unwrap_or("*");
+        match self.function {
+            AggregateFunction::Count => format!("COUNT({})", field),
+            // NUMERIC results are cast so they decode as JSON numbers
+            AggregateFunction::Sum => format!("SUM({})::double precision", field),
+            AggregateFunction::Avg => format!("AVG({})::double precision", field),
+            AggregateFunction::Min => format!("MIN({})", field),
+            AggregateFunction::Max => format!("MAX({})", field),
+        }
+    }
+}
+
+/// Grouped rows returned by the aggregate endpoints, keyed by the group and
+/// aggregate aliases, e.g. `{"country": "Indonesia", "count": 42}`.
+#[derive(Debug, Serialize)]
+pub struct AggregateResponse {
+    pub group_by: Vec<String>,
+    pub aggregates: Vec<String>,
+    pub results: Vec<Map<String, Value>>,
+}
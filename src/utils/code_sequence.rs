@@ -0,0 +1,279 @@
+use chrono::{DateTime, Datelike, Utc};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::AppError;
+
+/// Column holding the generated code.
+pub const CODE_COLUMN: &str = "code";
+
+/// Numbering of a resource's `code` column: `U-00001`, or `D-2025-00001` for a
+/// yearly sequence.
+///
+/// Numbers come from the `code_sequences` table and are allocated inside the
+/// insert transaction, so they are gap-free: concurrent inserts wait on the
+/// counter row, and a rolled back insert gives its number back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeSequence {
+    /// Prefix of rows no role applies to
+    pub prefix: &'static str,
+    /// Boolean columns numbered under their own prefix, e.g. `("is_supplier", "S")`.
+    /// The first one set on the inserted row wins.
+    pub roles: &'static [(&'static str, &'static str)],
+    /// Minimum digits of the number, zero-padded
+    pub padding: usize,
+    /// Restart at 1 every year, with the year in the code
+    pub yearly: bool,
+}
+
+impl CodeSequence {
+    /// Every prefix this sequence hands out.
+    pub fn prefixes(&self) -> Vec<&'static str> {
+        let mut prefixes = vec![self.prefix];
+        for (_, prefix) in self.roles {
+            if !prefixes.contains(prefix) {
+                prefixes.push(prefix);
+            }
+        }
+        prefixes
+    }
+
+    /// Prefix of a row inserted with `values`.
+    pub fn prefix_for(&self, values: &Map<String, Value>) -> &'static str {
+        self.roles
+            .iter()
+            .find(|(column, _)| values.get(*column) == Some(&Value::Bool(true)))
+            .map_or(self.prefix, |(_, prefix)| prefix)
+    }
+
+    /// Period numbering currently runs in: the year, or `""` when not yearly.
+    pub fn current_period(&self) -> String {
+        if self.yearly {
+            Utc::now().year().to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    /// Code `number` gets under `prefix` in `period`.
+    pub fn format(&self, prefix: &str, period: &str, number: i64) -> String {
+        format!(
+            "{}{:0width$}",
+            Self::code_start(prefix, period),
+            number,
+            width = self.padding
+        )
+    }
+
+    /// Allocates the next code for a row of `table` inserted with `values`. The
+    /// counter row stays locked until the transaction on `conn` ends.
+    pub async fn next(
+        &self,
+        conn: &mut PgConnection,
+        table: &str,
+        values: &Map<String, Value>,
+    ) -> Result<String, sqlx::Error> {
+        let prefix = self.prefix_for(values);
+        let period = self.current_period();
+
+        let (number,): (i64,) = sqlx::query_as(
+            "INSERT INTO code_sequences (entity, prefix, period, last_value) \
+             VALUES ($1, $2, $3, 1) \
+             ON CONFLICT (entity, prefix, period) \
+             DO UPDATE SET last_value = code_sequences.last_value + 1, updated_at = NOW() \
+             RETURNING last_value",
+        )
+        .bind(table)
+        .bind(prefix)
+        .bind(&period)
+        .fetch_one(conn)
+        .await?;
+
+        Ok(self.format(prefix, &period, number))
+    }
+
+    /// Every counter of `table`, plus a zero one for each prefix not used yet in
+    /// the current period.
+    pub async fn status(&self, db: &PgPool, table: &str) -> Result<Vec<SequenceStatus>, AppError> {
+        let rows: Vec<(String, String, i64, DateTime<Utc>)> = sqlx::query_as(
+            "SELECT prefix, period, last_value, updated_at FROM code_sequences \
+             WHERE entity = $1 ORDER BY prefix, period",
+        )
+        .bind(table)
+        .fetch_all(db)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        let period = self.current_period();
+        let mut counters: Vec<(String, String, i64, Option<DateTime<Utc>>)> = rows
+            .into_iter()
+            .map(|(prefix, period, last_value, updated_at)| {
+                (prefix, period, last_value, Some(updated_at))
+            })
+            .collect();
+        for prefix in self.prefixes() {
+            if !counters
+                .iter()
+                .any(|(p, q, ..)| p == prefix && *q == period)
+            {
+                counters.push((prefix.to_string(), period.clone(), 0, None));
+            }
+        }
+
+        Ok(counters
+            .into_iter()
+            .map(|(prefix, period, last_value, updated_at)| SequenceStatus {
+                entity: table.to_string(),
+                next_code: self.format(&prefix, &period, last_value + 1),
+                prefix,
+                period,
+                last_value,
+                updated_at,
+            })
+            .collect())
+    }
+
+    /// Sets the counter of `prefix` in `period` to `last_value`, so the next code
+    /// gets `last_value + 1`. Refuses values that would hand out a code `table`
+    /// already uses.
+    pub async fn reset(
+        &self,
+        db: &PgPool,
+        table: &str,
+        prefix: &str,
+        period: &str,
+        last_value: i64,
+        user_id: Uuid,
+    ) -> Result<(), AppError> {
+        if !self.prefixes().contains(&prefix) {
+            return Err(AppError::BadRequest(format!(
+                "{} codes have no {} prefix",
+                table, prefix
+            )));
+        }
+        if self.yearly != (period.len() == 4 && period.chars().all(|c| c.is_ascii_digit())) {
+            return Err(AppError::BadRequest(if self.yearly {
+                "period must be a year, e.g. 2025".to_string()
+            } else {
+                format!("{} codes are not numbered per period", table)
+            }));
+        }
+        if last_value < 0 {
+            return Err(AppError::BadRequest(
+                "last_value must not be negative".to_string(),
+            ));
+        }
+
+        // Highest number in use, trashed rows included
+        let start = Self::code_start(prefix, period);
+        let query = format!(
+            "SELECT COALESCE(MAX(SUBSTRING({col} FROM $2)::BIGINT), 0) FROM {} \
+             WHERE starts_with({col}, $1) AND SUBSTRING({col} FROM $2) ~ '^[0-9]+$'",
+            table,
+            col = CODE_COLUMN
+        );
+        let (in_use,): (i64,) = sqlx::query_as(&query)
+            .bind(&start)
+            .bind(start.len() as i32 + 1)
+            .fetch_one(db)
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        if last_value < in_use {
+            return Err(AppError::BadRequest(format!(
+                "{} is already in use; last_value must be at least {}",
+                self.format(prefix, period, in_use),
+                in_use
+            )));
+        }
+
+        sqlx::query(
+            "INSERT INTO code_sequences (entity, prefix, period, last_value, updated_by) \
+             VALUES ($1, $2, $3, $4, $5) \
+             ON CONFLICT (entity, prefix, period) \
+             DO UPDATE SET last_value = $4, updated_by = $5, updated_at = NOW()",
+        )
+        .bind(table)
+        .bind(prefix)
+        .bind(period)
+        .bind(last_value)
+        .bind(user_id)
+        .execute(db)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    // Everything before the number, e.g. `D-` or `D-2025-`
+    fn code_start(prefix: &str, period: &str) -> String {
+        if period.is_empty() {
+            format!("{}-", prefix)
+        } else {
+            format!("{}-{}-", prefix, period)
+        }
+    }
+}
+
+/// State of one counter, as shown to admins.
+#[derive(Debug, Serialize)]
+pub struct SequenceStatus {
+    pub entity: String,
+    pub prefix: String,
+    pub period: String,
+    pub last_value: i64,
+    /// Code the next insert gets
+    pub next_code: String,
+    /// Last allocation or reset; `None` before the first one
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const CONTACTS: CodeSequence = CodeSequence {
+        prefix: "C",
+        roles: &[("is_customer", "D"), ("is_supplier", "S")],
+        padding: 5,
+        yearly: false,
+    };
+
+    fn object(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => panic!("expected an object"),
+        }
+    }
+
+    #[test]
+    fn test_prefix_follows_first_role_set() {
+        assert_eq!(CONTACTS.prefixes(), ["C", "D", "S"]);
+        assert_eq!(
+            CONTACTS.prefix_for(&object(json!({"is_customer": true, "is_supplier": true}))),
+            "D"
+        );
+        assert_eq!(
+            CONTACTS.prefix_for(&object(json!({"is_customer": false, "is_supplier": true}))),
+            "S"
+        );
+        assert_eq!(CONTACTS.prefix_for(&object(json!({}))), "C");
+    }
+
+    #[test]
+    fn test_format_pads_number_and_adds_period() {
+        assert_eq!(CONTACTS.format("D", "", 42), "D-00042");
+        assert_eq!(CONTACTS.format("D", "", 1234567), "D-1234567");
+
+        let yearly = CodeSequence {
+            yearly: true,
+            padding: 4,
+            ..CONTACTS
+        };
+        assert_eq!(yearly.format("S", "2025", 7), "S-2025-0007");
+        assert_eq!(yearly.current_period(), Utc::now().year().to_string());
+        assert_eq!(CONTACTS.current_period(), "");
+    }
+}
@@ -0,0 +1,237 @@
+use serde_json::Value;
+use sqlx::{postgres::PgArguments, Arguments, PgPool};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+/// Column name -> type of one table.
+pub type TableColumns = HashMap<String, ColumnType>;
+
+/// Postgres type of a table column, as read from the catalog.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnType {
+    /// Type name usable in a cast, e.g. `numeric(12,2)`, `jsonb`, `text[]`
+    pub name: String,
+    /// `pg_type.typcategory`: `N` numeric, `B` boolean, `A` array, `S` string,
+    /// `D` date/time, `E` enum, `U` user-defined (uuid, json) ...
+    pub category: char,
+}
+
+impl ColumnType {
+    pub fn new(name: &str, category: char) -> Self {
+        Self {
+            name: name.to_string(),
+            category,
+        }
+    }
+
+    fn is_json(&self) -> bool {
+        self.name == "json" || self.name == "jsonb"
+    }
+
+    /// Type a filter value is cast to before comparing it with this column: the
+    /// name without its modifier, since casting to `varchar(20)` would truncate the
+    /// value. `None` for arrays and json, which aren't compared with scalars.
+    pub fn comparison_type(&self) -> Option<String> {
+        if self.category == 'A' || self.is_json() {
+            return None;
+        }
+
+        let mut name = String::with_capacity(self.name.len());
+        let mut depth = 0;
+        for c in self.name.chars() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                c if depth == 0 => name.push(c),
+                _ => {}
+            }
+        }
+        Some(name)
+    }
+
+    /// Whether a JSON value has the right shape for this column. Values that have
+    /// it can still be rejected by Postgres, e.g. a malformed date.
+    pub fn check(&self, value: &Value) -> Result<(), String> {
+        let fits = match (value, self.category) {
+            (Value::Null, _) => true,
+            _ if self.is_json() => true,
+            (Value::Array(items), 'A') => items
+                .iter()
+                .all(|item| !matches!(item, Value::Array(_) | Value::Object(_))),
+            (Value::Number(_) | Value::String(_), 'N') => true,
+            (Value::Bool(_), 'B') => true,
+            (Value::String(_), category) => !matches!(category, 'N' | 'B' | 'A'),
+            _ => false,
+        };
+
+        if fits {
+            Ok(())
+        } else {
+            let expected = match self.category {
+                'N' => "a number",
+                'B' => "a boolean",
+                'A' => "an array of scalar values",
+                _ => "a string",
+            };
+            Err(format!("expected {} for a {} column", expected, self.name))
+        }
+    }
+
+    /// Adds `value` to `args` as parameter `$position` and returns the placeholder,
+    /// cast to the column type (e.g. `$3::numeric(12,2)`).
+    ///
+    /// Values go over the wire as text (arrays as `text[]`) and Postgres parses them
+    /// with the column type's input function, so numerics keep their precision and
+    /// dates, enums and jsonb are converted server side.
+    pub fn bind(&self, value: Value, args: &mut PgArguments, position: usize) -> String {
+        match value {
+            Value::Array(items) if !self.is_json() => {
+                let elements: Vec<Option<String>> = items.into_iter().map(scalar_text).collect();
+                let _ = args.add(elements);
+            }
+            value if self.is_json() && !value.is_null() => {
+                let _ = args.add(value.to_string());
+            }
+            value => {
+                let _ = args.add(scalar_text(value));
+            }
+        }
+
+        format!("${}::{}", position, self.name)
+    }
+}
+
+/// Type of `field` in `columns`, the columns of `table`: bare or qualified with
+/// `table`. `None` for columns of other (joined) tables.
+pub fn lookup<'a>(columns: &'a TableColumns, table: &str, field: &str) -> Option<&'a ColumnType> {
+    let name = match field.split_once('.') {
+        Some((prefix, name)) if prefix == table => name,
+        Some(_) => return None,
+        None => field,
+    };
+    columns.get(name)
+}
+
+// Text form of a scalar JSON value; `null` stays NULL
+fn scalar_text(value: Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s),
+        other => Some(other.to_string()),
+    }
+}
+
+/// Column types of every table written through `CrudService`, loaded from the
+/// catalog on first use and kept for the life of the process.
+#[derive(Debug, Clone, Default)]
+pub struct ColumnTypeCache {
+    tables: Arc<RwLock<HashMap<String, Arc<TableColumns>>>>,
+}
+
+impl ColumnTypeCache {
+    pub async fn get(&self, db: &PgPool, table: &str) -> Result<Arc<TableColumns>, sqlx::Error> {
+        if let Some(columns) = self
+            .tables
+            .read()
+            .ok()
+            .and_then(|tables| tables.get(table).cloned())
+        {
+            return Ok(columns);
+        }
+
+        let rows: Vec<(String, String, String)> = sqlx::query_as(
+            "SELECT a.attname::text, format_type(a.atttypid, a.atttypmod), t.typcategory::text \
+             FROM pg_attribute a JOIN pg_type t ON t.oid = a.atttypid \
+             WHERE a.attrelid = $1::regclass AND a.attnum > 0 AND NOT a.attisdropped",
+        )
+        .bind(table)
+        .fetch_all(db)
+        .await?;
+
+        let columns: Arc<TableColumns> = Arc::new(
+            rows.into_iter()
+                .map(|(name, type_name, category)| {
+                    let category = category.chars().next().unwrap_or('X');
+                    (name, ColumnType::new(&type_name, category))
+                })
+                .collect(),
+        );
+
+        if let Ok(mut tables) = self.tables.write() {
+            tables.insert(table.to_string(), columns.clone());
+        }
+        Ok(columns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_check_matches_json_kind_to_column_category() {
+        let numeric = ColumnType::new("numeric(12,2)", 'N');
+        assert!(numeric.check(&json!(12.5)).is_ok());
+        assert!(numeric.check(&json!("12.50")).is_ok());
+        assert!(numeric.check(&json!(null)).is_ok());
+        assert_eq!(
+            numeric.check(&json!(true)),
+            Err("expected a number for a numeric(12,2) column".to_string())
+        );
+
+        let flag = ColumnType::new("boolean", 'B');
+        assert!(flag.check(&json!(false)).is_ok());
+        assert!(flag.check(&json!("true")).is_err());
+
+        let date = ColumnType::new("date", 'D');
+        assert!(date.check(&json!("2025-01-31")).is_ok());
+        assert!(date.check(&json!(20250131)).is_err());
+
+        let tags = ColumnType::new("text[]", 'A');
+        assert!(tags.check(&json!(["a", 1, null])).is_ok());
+        assert!(tags.check(&json!([["nested"]])).is_err());
+        assert!(tags.check(&json!("a")).is_err());
+
+        let data = ColumnType::new("jsonb", 'U');
+        assert!(data.check(&json!({"nested": [1, 2]})).is_ok());
+    }
+
+    #[test]
+    fn test_bind_casts_placeholder_to_column_type() {
+        let mut args = PgArguments::default();
+        assert_eq!(
+            ColumnType::new("numeric(12,2)", 'N').bind(json!(0.1), &mut args, 1),
+            "$1::numeric(12,2)"
+        );
+        assert_eq!(
+            ColumnType::new("integer[]", 'A').bind(json!([1, 2]), &mut args, 2),
+            "$2::integer[]"
+        );
+        assert_eq!(
+            ColumnType::new("jsonb", 'U').bind(json!({"a": 1}), &mut args, 3),
+            "$3::jsonb"
+        );
+        assert_eq!(args.len(), 3);
+    }
+
+    #[test]
+    fn test_comparison_type_drops_modifiers() {
+        assert_eq!(
+            ColumnType::new("character varying(100)", 'S').comparison_type(),
+            Some("character varying".to_string())
+        );
+        assert_eq!(
+            ColumnType::new("timestamp(3) with time zone", 'D').comparison_type(),
+            Some("timestamp with time zone".to_string())
+        );
+        assert_eq!(
+            ColumnType::new("uuid", 'U').comparison_type(),
+            Some("uuid".to_string())
+        );
+        assert_eq!(ColumnType::new("text[]", 'A').comparison_type(), None);
+        assert_eq!(ColumnType::new("jsonb", 'U').comparison_type(), None);
+    }
+}
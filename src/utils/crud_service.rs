@@ -1,119 +1,158 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     Extension, Json,
 };
 use chrono::{DateTime, Utc};
-use serde::{de::DeserializeOwned, Serialize};
-use sqlx::{postgres::PgArguments, Arguments, Column, Row};
-use std::collections::HashMap;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::{
+    postgres::PgArguments, Acquire, Arguments, Column, PgConnection, PgPool, Postgres, Row,
+    Transaction,
+};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::Arc,
+};
 use uuid::Uuid;
 
 use crate::{
     models::{AppState, AuthenticatedUser},
-    utils::query_builder::{PaginatedResponse, QueryBuilder, QueryParams},
-    AppError,
+    utils::{
+        aggregate::AggregateResponse,
+        code_sequence::{CodeSequence, CODE_COLUMN},
+        column_types::TableColumns,
+        etag,
+        fieldset::{column_name, resolve_fields, Sparse},
+        patch::{self, Patch},
+        query_builder::{PaginatedResponse, QueryParams, Trashed},
+        resource::Resource,
+    },
+    AppError, BulkItemError, FieldError,
 };
 
+/// Columns managed by `CrudService` itself, never written from request bodies.
+const PROTECTED_COLUMNS: &[&str] = &[
+    "id",
+    "created_by",
+    "updated_by",
+    "created_at",
+    "updated_at",
+    "deleted_at",
+    "deleted_by",
+];
+
+/// Most items accepted by one bulk request.
+pub const MAX_BULK_ITEMS: usize = 1000;
+
+// Postgres limit on bind parameters in a single statement
+const MAX_BIND_PARAMS: usize = 65535;
+
+/// Rows written by a bulk create/update, or ids removed by a bulk delete,
+/// in request order.
+#[derive(Debug, Serialize)]
+pub struct BulkResponse<T> {
+    pub count: usize,
+    pub results: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkDeleteRequest {
+    pub ids: Vec<Uuid>,
+}
+
 pub struct CrudService;
 
 impl CrudService {
-    // Generic GET handler with include relations support
-    pub async fn get_list_with_includes<T>(
-        table: &str,
-        select_fields: Vec<&str>,
-        searchable_fields: Vec<&str>,
-        filterable_fields: Vec<&str>,
-        sortable_fields: Vec<&str>,
-        joins: Vec<&str>,
-        includes: Vec<(&str, &str, Vec<&str>)>, // (name, join_clause, select_fields)
+    // Generic list handler for any resource
+    pub async fn list<R: Resource>(
         query: Query<QueryParams>,
         state: State<AppState>,
         base_url: &str,
-        _auth: Option<Extension<AuthenticatedUser>>,
-    ) -> Result<Json<PaginatedResponse<T>>, AppError>
-    where
-        T: DeserializeOwned + Send + Unpin,
-    {
-        let mut builder = QueryBuilder::new(table)
-            .select(select_fields)
-            .searchable(searchable_fields)
-            .filterable(filterable_fields)
-            .sortable(sortable_fields);
-
-        // Add static joins
-        for join in joins {
-            builder = builder.join(join);
-        }
-
-        // Add include relations
-        for (name, join_clause, include_fields) in includes {
-            builder = builder.include_relation(name, join_clause, include_fields);
-        }
-
-        let result = builder
-            .execute_with_base_url(&state.db, &query.0, base_url)
+        _auth: Option<Extension<AuthenticatedUser>>, // Optional auth
+    ) -> Result<Json<PaginatedResponse<Sparse<R::Response>>>, AppError> {
+        let column_types = Self::column_types(R::TABLE, &state).await?;
+        let result = R::query_builder()
+            .column_types(column_types)
+            .execute_with_base_url::<R::Row>(&state.db, &query.0, base_url)
             .await
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            .map_err(Self::write_error)?;
 
-        Ok(Json(result))
+        Ok(Json(PaginatedResponse {
+            count: result.count,
+            page_context: result.page_context,
+            links: result.links,
+            results: result
+                .results
+                .into_iter()
+                .map(|row| row.map(R::Response::from))
+                .collect(),
+        }))
     }
 
-    // Generic GET handler for any table
-    pub async fn get_list<T>(
-        table: &str,
-        select_fields: Vec<&str>,
-        searchable_fields: Vec<&str>,
-        filterable_fields: Vec<&str>,
-        sortable_fields: Vec<&str>,
-        joins: Vec<&str>,
+    // Generic grouped aggregate handler over the same search and filter pipeline
+    pub async fn aggregate<R: Resource>(
         query: Query<QueryParams>,
         state: State<AppState>,
-        base_url: &str,
-        _auth: Option<Extension<AuthenticatedUser>>, // Optional auth
-    ) -> Result<Json<PaginatedResponse<T>>, AppError>
-    where
-        T: DeserializeOwned + Send + Unpin,
-    {
-        let mut builder = QueryBuilder::new(table)
-            .select(select_fields)
-            .searchable(searchable_fields)
-            .filterable(filterable_fields)
-            .sortable(sortable_fields);
-
-        // Add joins
-        for join in joins {
-            builder = builder.join(join);
+        _auth: Option<Extension<AuthenticatedUser>>,
+    ) -> Result<Json<AggregateResponse>, AppError> {
+        let column_types = Self::column_types(R::TABLE, &state).await?;
+        let builder = R::query_builder().column_types(column_types);
+        let errors = builder.aggregate_errors(&query.0);
+        if !errors.is_empty() {
+            return Err(AppError::InvalidFields(errors));
         }
 
         let result = builder
-            .execute_with_base_url(&state.db, &query.0, base_url)
+            .execute_aggregate(&state.db, &query.0)
             .await
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            .map_err(Self::write_error)?;
 
         Ok(Json(result))
     }
 
-    // Generic GET by ID handler
-    pub async fn get_by_id<T>(
-        table: &str,
-        select_fields: Vec<&str>,
-        joins: Vec<&str>,
+    // Generic GET by ID handler; sends an ETag and answers a matching
+    // If-None-Match with 304 Not Modified
+    pub async fn get_by_id<R: Resource>(
+        params: &QueryParams,
         id: Path<Uuid>,
+        headers: &HeaderMap,
         state: State<AppState>,
         _auth: Option<Extension<AuthenticatedUser>>,
-    ) -> Result<Json<T>, AppError>
-    where
-        T: DeserializeOwned + Send + Unpin,
-    {
-        let select_clause = select_fields.join(", ");
-        let joins_clause = joins.join(" ");
+    ) -> Result<Response, AppError> {
+        let select_fields: Vec<String> = R::SELECT_FIELDS.iter().map(|s| s.to_string()).collect();
+        let fields = resolve_fields(params.fields.as_deref(), &select_fields);
 
-        let query = format!(
+        // Narrow the SELECT to the requested fieldset (always keeping `id`, and
+        // `updated_at` for the ETag)
+        let select_clause = match &fields {
+            Some(fields) => select_fields
+                .iter()
+                .filter(|select| {
+                    let name = column_name(select);
+                    name == "id" || name == "updated_at" || fields.iter().any(|field| field == name)
+                })
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", "),
+            None => select_fields.join(", "),
+        };
+        let joins_clause = R::JOINS.join(" ");
+
+        let mut query = format!(
             "SELECT {} FROM {} {} WHERE {}.id = $1",
-            select_clause, table, joins_clause, table
+            select_clause,
+            R::TABLE,
+            joins_clause,
+            R::TABLE
         );
+        if R::SOFT_DELETE {
+            let column = format!("{}.deleted_at", R::TABLE);
+            if let Some(condition) = Trashed::from_params(params).condition(&column) {
+                query.push_str(&format!(" AND {}", condition));
+            }
+        }
 
         let row = sqlx::query(&query)
             .bind(*id)
@@ -122,7 +161,20 @@ impl CrudService {
             .map_err(|e| AppError::DatabaseError(e.to_string()))?
             .ok_or(AppError::NotFound { id: *id })?;
 
-        // Convert row to JSON then to T
+        // Each fieldset is a representation of its own, with its own tag; includes
+        // aren't served here, so they don't change the body
+        let representation = etag::representation(fields.as_deref());
+        let updated_at = row.try_get::<DateTime<Utc>, _>("updated_at").ok();
+        let etag_header = updated_at
+            .map(|updated_at| etag::etag_header(updated_at, &representation))
+            .unwrap_or_default();
+        if let Some(updated_at) = updated_at {
+            if etag::if_none_match(headers, &etag::etag(updated_at, &representation)) {
+                return Ok((StatusCode::NOT_MODIFIED, etag_header).into_response());
+            }
+        }
+
+        // Convert row to JSON then to the row type
         let mut json_obj = serde_json::Map::new();
         for column in row.columns() {
             let column_name = column.name();
@@ -130,221 +182,329 @@ impl CrudService {
             json_obj.insert(column_name.to_string(), value);
         }
 
-        let item: T = serde_json::from_value(serde_json::Value::Object(json_obj))
+        let item: R::Row = serde_json::from_value(serde_json::Value::Object(json_obj))
             .map_err(|e| AppError::SerializationError(e.to_string()))?;
 
+        Ok((
+            etag_header,
+            Json(Sparse::new(R::Response::from(item), fields)),
+        )
+            .into_response())
+    }
+
+    // Generic CREATE handler: inserts the create fields present in `data`, each
+    // bound as its column type, numbering the row from `R::CODE_SEQUENCE`. The
+    // create hooks run in the insert's transaction.
+    pub async fn create<R: Resource>(
+        mut data: Map<String, Value>,
+        state: State<AppState>,
+        auth: Extension<AuthenticatedUser>,
+    ) -> Result<Json<R::Row>, AppError> {
+        let column_types = Self::column_types(R::TABLE, &state).await?;
+        Self::check_fields(&data, R::CREATE_FIELDS, &column_types)?;
+
+        let mut tx = state
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        R::before_create(&mut tx, &auth, &mut data).await?;
+        if let Some(sequence) = R::CODE_SEQUENCE {
+            Self::assign_code(&mut tx, R::TABLE, &sequence, &mut data).await?;
+        }
+
+        let columns: Vec<String> = data.keys().cloned().collect();
+        let (query, args, _) = Self::insert_statement(
+            R::TABLE,
+            &columns,
+            std::slice::from_ref(&data),
+            &column_types,
+            auth.user_id(),
+        );
+
+        let row = sqlx::query_with(&format!("{} RETURNING *", query), args)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(Self::write_error)?;
+
+        let item: R::Row = Self::row_to_item(&row)?;
+        R::after_create(&mut tx, &auth, &item).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
         Ok(Json(item))
     }
 
-    // Generic CREATE handler with dynamic field handling
-    pub async fn create<T, C>(
-        table: &str,
-        create_data: Json<C>,
+    // Generic UPDATE handler: writes the update fields present in the data.
+    // Honours If-Match and returns the new ETag.
+    pub async fn update<R: Resource>(
+        id: Path<Uuid>,
+        body: Value,
+        headers: &HeaderMap,
         state: State<AppState>,
         auth: Extension<AuthenticatedUser>,
-    ) -> Result<Json<T>, AppError>
-    where
-        T: DeserializeOwned + Send + Unpin,
-        C: Serialize,
-    {
-        // Convert create data to HashMap for dynamic insert
-        let data_value = serde_json::to_value(&create_data.0)
-            .map_err(|e| AppError::SerializationError(e.to_string()))?;
+    ) -> Result<(HeaderMap, Json<R::Row>), AppError> {
+        // A PUT replaces the row, so it must name every field; `null` clears one
+        let Value::Object(object) = &body else {
+            return Err(AppError::BadRequest(
+                "Request body must be a JSON object".to_string(),
+            ));
+        };
+        Self::check_body(&body, R::UPDATE_FIELDS)?;
+        let missing = Self::missing_fields(object, R::UPDATE_FIELDS);
+        if !missing.is_empty() {
+            return Err(AppError::InvalidFields(missing));
+        }
+        let update_data = serde_json::from_value::<R::Update>(body)
+            .map_err(|e| AppError::BadRequest(e.to_string()))?;
+        let changes = Self::to_object(&update_data)?;
 
-        let data_map: HashMap<String, serde_json::Value> = serde_json::from_value(data_value)
-            .map_err(|e| AppError::SerializationError(e.to_string()))?;
+        let tx = state
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
 
-        let mut columns = Vec::new();
-        let mut placeholders = Vec::new();
-        let mut args = PgArguments::default();
+        Self::apply_update::<R>(tx, *id, changes, headers, &state, &auth).await
+    }
 
-        // Add standard fields
-        columns.push("id".to_string());
-        placeholders.push("$1".to_string());
-        let new_id = Uuid::new_v4();
-        let _ = args.add(new_id);
+    // Generic UPSERT handler: inserts `data` as a create of `R`, or when a row with
+    // the same `key` exists, overwrites the update fields among `sent`, the members
+    // of the request body, leaving what `create_values` filled in alone. `key` must
+    // have a unique index and is writable here even when creates can't set it. A
+    // trashed row with the key is a conflict. Runs the create hooks before the
+    // write and the create or update one after it. Returns whether the row was
+    // created.
+    pub async fn upsert<R: Resource>(
+        key: &str,
+        mut data: Map<String, Value>,
+        sent: &[String],
+        state: State<AppState>,
+        auth: Extension<AuthenticatedUser>,
+    ) -> Result<(bool, HeaderMap, Json<R::Row>), AppError> {
+        let table = R::TABLE;
+        if data.get(key).is_none_or(Value::is_null) {
+            return Err(AppError::BadRequest(format!("{} is required", key)));
+        }
 
-        columns.push("created_by".to_string());
-        placeholders.push("$2".to_string());
-        let _ = args.add(auth.user_id());
+        let column_types = Self::column_types(table, &state).await?;
+        let mut writable = R::CREATE_FIELDS.to_vec();
+        writable.push(key);
+        Self::check_fields(&data, &writable, &column_types)?;
 
-        columns.push("updated_by".to_string());
-        placeholders.push("$3".to_string());
-        let _ = args.add(auth.user_id());
+        let mut tx = state
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
 
-        // Add dynamic fields
-        let mut param_count = 4;
-        for (key, value) in data_map {
-            if key != "id"
-                && key != "created_by"
-                && key != "updated_by"
-                && key != "created_at"
-                && key != "updated_at"
-            {
-                columns.push(key.clone());
-                placeholders.push(format!("${}", param_count));
+        R::before_create(&mut tx, &auth, &mut data).await?;
+        // As the hook left it, e.g. normalized
+        let key_value = data.get(key).cloned().unwrap_or_default();
 
-                match value {
-                    serde_json::Value::String(s) => {
-                        let _ = args.add(s);
-                    }
-                    serde_json::Value::Number(n) => {
-                        if let Some(i) = n.as_i64() {
-                            let _ = args.add(i);
-                        } else if let Some(f) = n.as_f64() {
-                            let _ = args.add(f);
-                        } else {
-                            let _ = args.add(0i64); // fallback
-                        }
-                    }
-                    serde_json::Value::Bool(b) => {
-                        let _ = args.add(b);
-                    }
-                    serde_json::Value::Null => {
-                        let _ = args.add(Option::<String>::None);
-                    }
-                    _ => {
-                        let _ = args.add(value.to_string()); // Fallback to string
-                    }
-                }
-                param_count += 1;
+        // Only an insert may use up a number: allocate under a savepoint and give
+        // it back if the row exists. Holding the counter lock, every insert that
+        // raced ahead of this one has committed and is visible.
+        if let Some(sequence) = R::CODE_SEQUENCE.filter(|_| !data.contains_key(CODE_COLUMN)) {
+            let mut savepoint = Acquire::begin(&mut tx)
+                .await
+                .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            let code = sequence
+                .next(&mut savepoint, table, &data)
+                .await
+                .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+            let mut args = PgArguments::default();
+            let placeholder = match column_types.get(key) {
+                Some(column_type) => column_type.bind(key_value, &mut args, 1),
+                None => return Err(AppError::BadRequest(format!("{} is not a column", key))),
+            };
+            let query = format!(
+                "SELECT EXISTS(SELECT 1 FROM {} WHERE {} = {})",
+                table, key, placeholder
+            );
+            let (exists,): (bool,) = sqlx::query_as_with(&query, args)
+                .fetch_one(&mut *savepoint)
+                .await
+                .map_err(Self::write_error)?;
+
+            if exists {
+                savepoint.rollback().await
+            } else {
+                data.insert(CODE_COLUMN.to_string(), Value::String(code));
+                savepoint.commit().await
             }
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
         }
 
-        let query = format!(
-            "INSERT INTO {} ({}) VALUES ({}) RETURNING *",
-            table,
-            columns.join(", "),
-            placeholders.join(", ")
-        );
+        let updatable: Vec<&str> = R::UPDATE_FIELDS
+            .iter()
+            .copied()
+            .filter(|field| sent.iter().any(|member| member == field))
+            .filter(|field| !R::SERVER_DEFAULTS.contains(field))
+            .collect();
+        let (query, args) =
+            Self::upsert_statement::<R>(key, &data, &updatable, &column_types, auth.user_id());
 
+        // The DO UPDATE skips trashed rows, which leaves nothing to return
         let row = sqlx::query_with(&query, args)
-            .fetch_one(&state.db)
+            .fetch_optional(&mut *tx)
             .await
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            .map_err(Self::write_error)?
+            .ok_or_else(|| {
+                AppError::Conflict(format!(
+                    "A trashed row has this {}; restore or purge it first",
+                    key
+                ))
+            })?;
 
-        // Convert to T
-        let mut json_obj = serde_json::Map::new();
-        for column in row.columns() {
-            let column_name = column.name();
-            let value = Self::row_value_to_json(&row, column_name);
-            json_obj.insert(column_name.to_string(), value);
+        let created = row.try_get::<bool, _>("inserted").unwrap_or(false);
+        let item: R::Row = Self::row_to_item(&row)?;
+        if created {
+            R::after_create(&mut tx, &auth, &item).await?;
+        } else {
+            R::after_update(&mut tx, &auth, &item).await?;
         }
 
-        let item: T = serde_json::from_value(serde_json::Value::Object(json_obj))
-            .map_err(|e| AppError::SerializationError(e.to_string()))?;
+        tx.commit()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
 
-        Ok(Json(item))
+        let etag_header = row
+            .try_get::<DateTime<Utc>, _>("updated_at")
+            .map(|updated_at| etag::etag_header(updated_at, ""))
+            .unwrap_or_default();
+
+        Ok((created, etag_header, Json(item)))
     }
 
-    // Generic UPDATE handler with dynamic field handling
-    pub async fn update<T, U>(
-        table: &str,
+    // Generic PATCH handler: writes only the columns the patch touches, which must
+    // all be update fields. Honours If-Match and returns the new ETag.
+    pub async fn patch<R: Resource>(
         id: Path<Uuid>,
-        update_data: Json<U>,
+        patch: Patch,
+        headers: &HeaderMap,
         state: State<AppState>,
         auth: Extension<AuthenticatedUser>,
-    ) -> Result<Json<T>, AppError>
-    where
-        T: DeserializeOwned + Send + Unpin,
-        U: Serialize,
-    {
-        // Convert update data to HashMap for dynamic update
-        let data_value = serde_json::to_value(&update_data.0)
-            .map_err(|e| AppError::SerializationError(e.to_string()))?;
+    ) -> Result<(HeaderMap, Json<R::Row>), AppError> {
+        let mut tx = state
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
 
-        let data_map: HashMap<String, serde_json::Value> = serde_json::from_value(data_value)
-            .map_err(|e| AppError::SerializationError(e.to_string()))?;
+        let changes = match patch {
+            Patch::Merge(patch) => patch::merge_patch_changes(patch)?,
+            Patch::Json(operations) => {
+                // JSON Patch applies to the current row, locked until the update
+                let query = format!(
+                    "SELECT * FROM {} WHERE {} FOR UPDATE",
+                    R::TABLE,
+                    Self::live_row::<R>("$1").join(" AND ")
+                );
+                let row = sqlx::query(&query)
+                    .bind(*id)
+                    .fetch_optional(&mut *tx)
+                    .await
+                    .map_err(|e| AppError::DatabaseError(e.to_string()))?
+                    .ok_or(AppError::NotFound { id: *id })?;
 
-        if data_map.is_empty() {
-            return Err(AppError::BadRequest("No fields to update".to_string()));
-        }
+                patch::json_patch_changes(Self::row_to_item(&row)?, &operations)?
+            }
+        };
 
-        let mut set_clauses = Vec::new();
+        Self::apply_update::<R>(tx, *id, changes, headers, &state, &auth).await
+    }
+
+    // Generic DELETE handler, honouring If-Match. Soft-deleting resources only
+    // stamp deleted_at/deleted_by, leaving the row in place. The delete hooks run
+    // in the delete's transaction.
+    pub async fn delete<R: Resource>(
+        id: Path<Uuid>,
+        headers: &HeaderMap,
+        state: State<AppState>,
+        auth: Extension<AuthenticatedUser>,
+    ) -> Result<Response, AppError> {
         let mut args = PgArguments::default();
+        let _ = args.add(*id);
+        let mut query = if R::SOFT_DELETE {
+            let _ = args.add(auth.user_id());
+            format!(
+                "UPDATE {} SET deleted_at = NOW(), deleted_by = $2 WHERE id = $1 AND deleted_at IS NULL",
+                R::TABLE
+            )
+        } else {
+            format!("DELETE FROM {} WHERE id = $1", R::TABLE)
+        };
 
-        // Add standard fields
-        set_clauses.push("updated_by = $1".to_string());
-        let _ = args.add(auth.user_id());
+        let versions = etag::if_match(headers);
+        if let Some(versions) = &versions {
+            let _ = args.add(versions.clone());
+            query.push_str(&format!(" AND updated_at = ANY(${})", args.len()));
+        }
 
-        set_clauses.push("updated_at = NOW()".to_string());
+        let mut tx = state
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
 
-        // Add dynamic fields
-        let mut param_count = 2;
-        for (key, value) in data_map {
-            if key != "id"
-                && key != "created_by"
-                && key != "updated_by"
-                && key != "created_at"
-                && key != "updated_at"
-            {
-                set_clauses.push(format!("{} = ${}", key, param_count));
-
-                match value {
-                    serde_json::Value::String(s) => {
-                        let _ = args.add(s);
-                    }
-                    serde_json::Value::Number(n) => {
-                        if let Some(i) = n.as_i64() {
-                            let _ = args.add(i);
-                        } else if let Some(f) = n.as_f64() {
-                            let _ = args.add(f);
-                        } else {
-                            let _ = args.add(0i64); // fallback
-                        }
-                    }
-                    serde_json::Value::Bool(b) => {
-                        let _ = args.add(b);
-                    }
-                    serde_json::Value::Null => {
-                        let _ = args.add(Option::<String>::None);
-                    }
-                    _ => {
-                        let _ = args.add(value.to_string()); // Fallback to string
-                    }
-                }
-                param_count += 1;
-            }
+        R::before_delete(&mut tx, &auth, *id).await?;
+
+        let result = sqlx::query_with(&query, args)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(Self::missing_or_stale::<R>(*id, &versions, &state.db).await);
         }
 
-        // Add ID for WHERE clause
-        let _ = args.add(*id);
+        R::after_delete(&mut tx, &auth, *id).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        Ok((StatusCode::NO_CONTENT).into_response())
+    }
 
+    // Brings a soft-deleted row back
+    pub async fn restore<R: Resource>(
+        id: Path<Uuid>,
+        state: State<AppState>,
+        auth: Extension<AuthenticatedUser>,
+    ) -> Result<Json<R::Row>, AppError> {
         let query = format!(
-            "UPDATE {} SET {} WHERE id = ${} RETURNING *",
-            table,
-            set_clauses.join(", "),
-            param_count
+            "UPDATE {} SET deleted_at = NULL, deleted_by = NULL, updated_by = $1, updated_at = NOW() \
+             WHERE id = $2 AND deleted_at IS NOT NULL RETURNING *",
+            R::TABLE
         );
 
-        let row = sqlx::query_with(&query, args)
+        let row = sqlx::query(&query)
+            .bind(auth.user_id())
+            .bind(*id)
             .fetch_optional(&state.db)
             .await
             .map_err(|e| AppError::DatabaseError(e.to_string()))?
             .ok_or(AppError::NotFound { id: *id })?;
 
-        // Convert to T
-        let mut json_obj = serde_json::Map::new();
-        for column in row.columns() {
-            let column_name = column.name();
-            let value = Self::row_value_to_json(&row, column_name);
-            json_obj.insert(column_name.to_string(), value);
-        }
-
-        let item: T = serde_json::from_value(serde_json::Value::Object(json_obj))
-            .map_err(|e| AppError::SerializationError(e.to_string()))?;
-
-        Ok(Json(item))
+        Ok(Json(Self::row_to_item(&row)?))
     }
 
-    // Generic DELETE handler
-    pub async fn delete(
-        table: &str,
+    // Permanently removes a row that is already soft-deleted
+    pub async fn purge<R: Resource>(
         id: Path<Uuid>,
         state: State<AppState>,
         _auth: Extension<AuthenticatedUser>,
     ) -> Result<Response, AppError> {
-        let query = format!("DELETE FROM {} WHERE id = $1", table);
+        let query = format!(
+            "DELETE FROM {} WHERE id = $1 AND deleted_at IS NOT NULL",
+            R::TABLE
+        );
 
         let result = sqlx::query(&query)
             .bind(*id)
@@ -359,6 +519,725 @@ impl CrudService {
         Ok((StatusCode::NO_CONTENT).into_response())
     }
 
+    // Bulk INSERT in one transaction, using multi-row VALUES chunked under the
+    // bind parameter limit. All rows are written or none are; numbers taken from
+    // `R::CODE_SEQUENCE` follow item order.
+    pub async fn bulk_create<R: Resource>(
+        mut items: Vec<Map<String, Value>>,
+        state: State<AppState>,
+        auth: Extension<AuthenticatedUser>,
+    ) -> Result<Json<BulkResponse<R::Row>>, AppError> {
+        Self::check_bulk_size(items.len())?;
+
+        let table = R::TABLE;
+        let column_types = Self::column_types(table, &state).await?;
+        let errors: Vec<BulkItemError> = items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| {
+                let fields = Self::field_errors(item, R::CREATE_FIELDS, &column_types);
+                (!fields.is_empty()).then(|| BulkItemError {
+                    index,
+                    id: None,
+                    error: "Invalid fields".to_string(),
+                    fields,
+                })
+            })
+            .collect();
+        if !errors.is_empty() {
+            return Err(AppError::BulkFailed(errors));
+        }
+
+        let mut tx = state
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        let mut errors = Vec::new();
+        for (index, item) in items.iter_mut().enumerate() {
+            if let Err(e) = R::before_create(&mut tx, &auth, item).await {
+                errors.push(Self::bulk_item_error(index, None, e));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(AppError::BulkFailed(errors));
+        }
+
+        if let Some(sequence) = R::CODE_SEQUENCE {
+            for item in &mut items {
+                Self::assign_code(&mut tx, table, &sequence, item).await?;
+            }
+        }
+
+        // Every column any item sets; items that omit one get its DEFAULT
+        let columns: Vec<String> = items
+            .iter()
+            .flat_map(|item| item.keys())
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let rows_per_statement = (MAX_BIND_PARAMS / (columns.len() + 3)).max(1);
+
+        let mut results = Vec::with_capacity(items.len());
+        for chunk in items.chunks(rows_per_statement) {
+            let (query, args, ids) =
+                Self::insert_statement(table, &columns, chunk, &column_types, auth.user_id());
+
+            let mut rows = sqlx::query_with(&format!("{} RETURNING *", query), args)
+                .fetch_all(&mut *tx)
+                .await
+                .map_err(Self::write_error)?;
+
+            // RETURNING isn't guaranteed to follow the VALUES order
+            Self::sort_by_ids(&mut rows, &ids, |row| row.try_get("id").ok());
+            for row in &rows {
+                results.push(Self::row_to_item(row)?);
+            }
+        }
+
+        for (index, item) in results.iter().enumerate() {
+            if let Err(e) = R::after_create(&mut tx, &auth, item).await {
+                errors.push(Self::bulk_item_error(index, None, e));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(AppError::BulkFailed(errors));
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        Ok(Json(BulkResponse {
+            count: results.len(),
+            results,
+        }))
+    }
+
+    // Bulk UPDATE in one transaction. Each item runs under its own savepoint so
+    // every failure is reported; any failure rolls the whole batch back.
+    pub async fn bulk_update<R: Resource>(
+        items: Vec<(Uuid, Map<String, Value>)>,
+        state: State<AppState>,
+        auth: Extension<AuthenticatedUser>,
+    ) -> Result<Json<BulkResponse<R::Row>>, AppError> {
+        Self::check_bulk_size(items.len())?;
+
+        let column_types = Self::column_types(R::TABLE, &state).await?;
+        let mut tx = state
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        let mut results = Vec::with_capacity(items.len());
+        let mut errors = Vec::new();
+        for (index, (id, mut data)) in items.into_iter().enumerate() {
+            if data.is_empty() {
+                errors.push(Self::bulk_item_error(
+                    index,
+                    Some(id),
+                    AppError::BadRequest("No fields to update".to_string()),
+                ));
+                continue;
+            }
+            if let Err(e) = Self::check_fields(&data, R::UPDATE_FIELDS, &column_types) {
+                errors.push(Self::bulk_item_error(index, Some(id), e));
+                continue;
+            }
+
+            let mut savepoint = Acquire::begin(&mut tx)
+                .await
+                .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            let written: Result<R::Row, AppError> = async {
+                R::before_update(&mut savepoint, &auth, id, &mut data).await?;
+
+                let (query, args) =
+                    Self::update_statement::<R>(data, &column_types, auth.user_id(), id, None);
+                let row = sqlx::query_with(&query, args)
+                    .fetch_optional(&mut *savepoint)
+                    .await
+                    .map_err(Self::write_error)?
+                    .ok_or(AppError::NotFound { id })?;
+
+                let item = Self::row_to_item(&row)?;
+                R::after_update(&mut savepoint, &auth, &item).await?;
+                Ok(item)
+            }
+            .await;
+
+            match written {
+                Ok(item) => {
+                    savepoint
+                        .commit()
+                        .await
+                        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+                    results.push(item);
+                }
+                Err(e) => errors.push(Self::bulk_item_error(index, Some(id), e)),
+            }
+        }
+
+        // Dropping `tx` without committing rolls everything back
+        if !errors.is_empty() {
+            return Err(AppError::BulkFailed(errors));
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        Ok(Json(BulkResponse {
+            count: results.len(),
+            results,
+        }))
+    }
+
+    // Bulk DELETE (or soft delete) of an id list in one statement; fails without
+    // deleting anything if any id does not exist.
+    pub async fn bulk_delete<R: Resource>(
+        ids: Vec<Uuid>,
+        state: State<AppState>,
+        auth: Extension<AuthenticatedUser>,
+    ) -> Result<Json<BulkResponse<Uuid>>, AppError> {
+        Self::check_bulk_size(ids.len())?;
+
+        let mut tx = state
+            .db
+            .begin()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        // Each id once, in request order, with the index it first appears at
+        let mut seen = HashSet::new();
+        let unique: Vec<(usize, Uuid)> = ids
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, id)| seen.insert(*id))
+            .collect();
+
+        let mut errors = Vec::new();
+        for &(index, id) in &unique {
+            if let Err(e) = R::before_delete(&mut tx, &auth, id).await {
+                errors.push(Self::bulk_item_error(index, Some(id), e));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(AppError::BulkFailed(errors));
+        }
+
+        let query = if R::SOFT_DELETE {
+            format!(
+                "UPDATE {} SET deleted_at = NOW(), deleted_by = $2 \
+                 WHERE id = ANY($1) AND deleted_at IS NULL RETURNING id",
+                R::TABLE
+            )
+        } else {
+            format!("DELETE FROM {} WHERE id = ANY($1) RETURNING id", R::TABLE)
+        };
+        let mut statement = sqlx::query_scalar(&query).bind(&ids);
+        if R::SOFT_DELETE {
+            statement = statement.bind(auth.user_id());
+        }
+        let deleted: HashSet<Uuid> = statement
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?
+            .into_iter()
+            .collect();
+
+        let errors: Vec<BulkItemError> = ids
+            .iter()
+            .enumerate()
+            .filter(|(_, id)| !deleted.contains(id))
+            .map(|(index, id)| {
+                Self::bulk_item_error(index, Some(*id), AppError::NotFound { id: *id })
+            })
+            .collect();
+        if !errors.is_empty() {
+            return Err(AppError::BulkFailed(errors));
+        }
+
+        let mut errors = Vec::new();
+        for &(index, id) in &unique {
+            if let Err(e) = R::after_delete(&mut tx, &auth, id).await {
+                errors.push(Self::bulk_item_error(index, Some(id), e));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(AppError::BulkFailed(errors));
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        let results: Vec<Uuid> = unique.into_iter().map(|(_, id)| id).collect();
+        Ok(Json(BulkResponse {
+            count: results.len(),
+            results,
+        }))
+    }
+
+    // Shared tail of PUT and PATCH: checks `changes`, runs the update hooks around
+    // the UPDATE and commits `tx`
+    async fn apply_update<R: Resource>(
+        mut tx: Transaction<'_, Postgres>,
+        id: Uuid,
+        mut changes: Map<String, Value>,
+        headers: &HeaderMap,
+        state: &AppState,
+        auth: &AuthenticatedUser,
+    ) -> Result<(HeaderMap, Json<R::Row>), AppError> {
+        if changes.is_empty() {
+            return Err(AppError::BadRequest("No fields to update".to_string()));
+        }
+
+        let column_types = Self::column_types(R::TABLE, state).await?;
+        Self::check_fields(&changes, R::UPDATE_FIELDS, &column_types)?;
+
+        R::before_update(&mut tx, auth, id, &mut changes).await?;
+
+        let versions = etag::if_match(headers);
+        let (query, args) = Self::update_statement::<R>(
+            changes,
+            &column_types,
+            auth.user_id(),
+            id,
+            versions.as_deref(),
+        );
+
+        let row = sqlx::query_with(&query, args)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(Self::write_error)?;
+        let Some(row) = row else {
+            return Err(Self::missing_or_stale::<R>(id, &versions, &state.db).await);
+        };
+
+        let item: R::Row = Self::row_to_item(&row)?;
+        R::after_update(&mut tx, auth, &item).await?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        let etag_header = row
+            .try_get::<DateTime<Utc>, _>("updated_at")
+            .map(|updated_at| etag::etag_header(updated_at, ""))
+            .unwrap_or_default();
+
+        Ok((etag_header, Json(item)))
+    }
+
+    // Why a conditional write matched no row: NotFound, or PreconditionFailed when
+    // the row exists but If-Match named other versions of it. A trashed row is
+    // NotFound, as for get-by-id.
+    async fn missing_or_stale<R: Resource>(
+        id: Uuid,
+        versions: &Option<Vec<DateTime<Utc>>>,
+        db: &PgPool,
+    ) -> AppError {
+        if versions.is_none() {
+            return AppError::NotFound { id };
+        }
+
+        let query = format!(
+            "SELECT EXISTS (SELECT 1 FROM {} WHERE {})",
+            R::TABLE,
+            Self::live_row::<R>("$1").join(" AND ")
+        );
+        match sqlx::query_scalar::<_, bool>(&query)
+            .bind(id)
+            .fetch_one(db)
+            .await
+        {
+            Ok(true) => AppError::PreconditionFailed,
+            Ok(false) => AppError::NotFound { id },
+            Err(e) => AppError::DatabaseError(e.to_string()),
+        }
+    }
+
+    // Conditions matching the row whose id is `id_placeholder` when writes may
+    // touch it: a soft-deleted row has to be restored first
+    fn live_row<R: Resource>(id_placeholder: &str) -> Vec<String> {
+        let mut conditions = vec![format!("{}.id = {}", R::TABLE, id_placeholder)];
+        if R::SOFT_DELETE {
+            let column = format!("{}.deleted_at", R::TABLE);
+            conditions.extend(Trashed::Without.condition(&column));
+        }
+        conditions
+    }
+
+    // A request body as its column -> value object
+    fn to_object<D: Serialize>(data: &D) -> Result<Map<String, Value>, AppError> {
+        match serde_json::to_value(data) {
+            Ok(Value::Object(object)) => Ok(object),
+            Ok(_) => Err(AppError::BadRequest(
+                "Request body must be a JSON object".to_string(),
+            )),
+            Err(e) => Err(AppError::SerializationError(e.to_string())),
+        }
+    }
+
+    async fn column_types(table: &str, state: &AppState) -> Result<Arc<TableColumns>, AppError> {
+        state
+            .column_types
+            .get(&state.db, table)
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))
+    }
+
+    // Fields of `required` that `data` leaves out
+    fn missing_fields(data: &Map<String, Value>, required: &[&str]) -> Vec<FieldError> {
+        required
+            .iter()
+            .filter(|field| !data.contains_key(**field))
+            .map(|field| FieldError {
+                field: field.to_string(),
+                error: "is required; send null to clear it".to_string(),
+            })
+            .collect()
+    }
+
+    // Members of `data` that can't be written: server-managed, non-writable or
+    // unknown columns, and values of the wrong kind for their column
+    fn field_errors(
+        data: &Map<String, Value>,
+        writable: &[&str],
+        column_types: &TableColumns,
+    ) -> Vec<FieldError> {
+        data.iter()
+            .filter_map(|(field, value)| {
+                let error = match Self::member_error(field, writable) {
+                    Some(error) => error.to_string(),
+                    None => match column_types.get(field) {
+                        Some(column_type) => column_type.check(value).err()?,
+                        None => "not a column".to_string(),
+                    },
+                };
+                Some(FieldError {
+                    field: field.clone(),
+                    error,
+                })
+            })
+            .collect()
+    }
+
+    // Why a request may not send `field`, if it may not
+    fn member_error(field: &str, writable: &[&str]) -> Option<&'static str> {
+        if PROTECTED_COLUMNS.contains(&field) {
+            Some("managed by the server")
+        } else if !writable.contains(&field) {
+            Some("not a writable field")
+        } else {
+            None
+        }
+    }
+
+    /// Members of a request body outside `accepted`. Checked before the body is
+    /// read into its DTO, which would silently drop them.
+    pub fn body_errors(body: &Value, accepted: &[&str]) -> Vec<FieldError> {
+        let Value::Object(members) = body else {
+            return Vec::new();
+        };
+        members
+            .keys()
+            .filter_map(|field| {
+                Some(FieldError {
+                    field: field.clone(),
+                    error: Self::member_error(field, accepted)?.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Rejects a request body with members outside `accepted` with a `400`
+    /// listing each of them.
+    pub fn check_body(body: &Value, accepted: &[&str]) -> Result<(), AppError> {
+        let errors = Self::body_errors(body, accepted);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::InvalidFields(errors))
+        }
+    }
+
+    fn check_fields(
+        data: &Map<String, Value>,
+        writable: &[&str],
+        column_types: &TableColumns,
+    ) -> Result<(), AppError> {
+        let errors = Self::field_errors(data, writable, column_types);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::InvalidFields(errors))
+        }
+    }
+
+    // Gives `data` the next code of `sequence` unless it already has one
+    async fn assign_code(
+        conn: &mut PgConnection,
+        table: &str,
+        sequence: &CodeSequence,
+        data: &mut Map<String, Value>,
+    ) -> Result<(), AppError> {
+        if data.get(CODE_COLUMN).is_some_and(|code| !code.is_null()) {
+            return Ok(());
+        }
+
+        let code = sequence
+            .next(conn, table, data)
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        data.insert(CODE_COLUMN.to_string(), Value::String(code));
+        Ok(())
+    }
+
+    // Values Postgres can't convert to the column type (SQLSTATE class 22, data
+    // exception) are the client's fault, whether written or filtered on
+    fn write_error(e: sqlx::Error) -> AppError {
+        let Some(db) = e.as_database_error() else {
+            return AppError::DatabaseError(e.to_string());
+        };
+        match db.code().as_deref() {
+            // Data exceptions, and not-null or check violations: the client sent a
+            // value the column can't hold
+            Some(code) if code.starts_with("22") || code == "23502" || code == "23514" => {
+                AppError::BadRequest(db.message().to_string())
+            }
+            // Unique violation: another row already has the value
+            Some("23505") => AppError::Conflict(db.message().to_string()),
+            _ => AppError::DatabaseError(e.to_string()),
+        }
+    }
+
+    // Per-item report of an error from writing item `index` of a bulk request
+    fn bulk_item_error(index: usize, id: Option<Uuid>, e: AppError) -> BulkItemError {
+        let (error, fields) = match e {
+            AppError::InvalidFields(fields) => ("Invalid fields".to_string(), fields),
+            AppError::NotFound { id } => (format!("Resource with id {} not found", id), Vec::new()),
+            AppError::BadRequest(message)
+            | AppError::DatabaseError(message)
+            | AppError::SerializationError(message)
+            | AppError::InternalServerError(message)
+            | AppError::UnhandledError(message)
+            | AppError::Conflict(message)
+            | AppError::Forbidden(message) => (message, Vec::new()),
+            AppError::PreconditionFailed => (
+                "The resource was modified since it was read".to_string(),
+                Vec::new(),
+            ),
+            AppError::LoginFailed | AppError::UnAuthorized | AppError::CookieFormatError => {
+                ("Not authorized".to_string(), Vec::new())
+            }
+            AppError::BulkFailed(_) => ("The item could not be written".to_string(), Vec::new()),
+        };
+        BulkItemError {
+            index,
+            id,
+            error,
+            fields,
+        }
+    }
+
+    fn check_bulk_size(len: usize) -> Result<(), AppError> {
+        if len == 0 {
+            return Err(AppError::BadRequest("No items to process".to_string()));
+        }
+        if len > MAX_BULK_ITEMS {
+            return Err(AppError::BadRequest(format!(
+                "At most {} items are allowed per bulk request",
+                MAX_BULK_ITEMS
+            )));
+        }
+        Ok(())
+    }
+
+    // Orders `items` like `ids`, by the id each one has; items without a known id
+    // go last
+    fn sort_by_ids<T>(items: &mut [T], ids: &[Uuid], id_of: impl Fn(&T) -> Option<Uuid>) {
+        let positions: HashMap<Uuid, usize> = ids
+            .iter()
+            .enumerate()
+            .map(|(index, id)| (*id, index))
+            .collect();
+        items.sort_by_key(|item| {
+            id_of(item)
+                .and_then(|id| positions.get(&id).copied())
+                .unwrap_or(usize::MAX)
+        });
+    }
+
+    // Multi-row `INSERT` of `rows` over `columns`, plus the standard
+    // id/created_by/updated_by columns, and the ids given to the rows in order;
+    // callers add the `RETURNING` clause
+    fn insert_statement(
+        table: &str,
+        columns: &[String],
+        rows: &[Map<String, Value>],
+        column_types: &TableColumns,
+        user_id: Uuid,
+    ) -> (String, PgArguments, Vec<Uuid>) {
+        let mut args = PgArguments::default();
+        let mut param_count = 1;
+        let mut values = Vec::with_capacity(rows.len());
+        let mut ids = Vec::with_capacity(rows.len());
+
+        for row in rows {
+            let mut placeholders = Vec::with_capacity(columns.len() + 3);
+
+            // Add standard fields
+            let id = Uuid::new_v4();
+            ids.push(id);
+            for value in [id, user_id, user_id] {
+                placeholders.push(format!("${}", param_count));
+                let _ = args.add(value);
+                param_count += 1;
+            }
+
+            // Add dynamic fields
+            for column in columns {
+                match (row.get(column), column_types.get(column)) {
+                    (Some(value), Some(column_type)) => {
+                        placeholders.push(column_type.bind(value.clone(), &mut args, param_count));
+                        param_count += 1;
+                    }
+                    _ => placeholders.push("DEFAULT".to_string()),
+                }
+            }
+
+            values.push(format!("({})", placeholders.join(", ")));
+        }
+
+        let mut all_columns = vec!["id", "created_by", "updated_by"];
+        all_columns.extend(columns.iter().map(String::as_str));
+
+        let query = format!(
+            "INSERT INTO {} ({}) VALUES {}",
+            table,
+            all_columns.join(", "),
+            values.join(", ")
+        );
+
+        (query, args, ids)
+    }
+
+    // `INSERT ... ON CONFLICT (key) DO UPDATE` of one row of `R`. On conflict only the
+    // `updatable` columns present in `data` are overwritten, and only on a live row;
+    // `RETURNING` adds an `inserted` flag (`xmax` is 0 only for a freshly inserted tuple).
+    fn upsert_statement<R: Resource>(
+        key: &str,
+        data: &Map<String, Value>,
+        updatable: &[&str],
+        column_types: &TableColumns,
+        user_id: Uuid,
+    ) -> (String, PgArguments) {
+        let columns: Vec<String> = data.keys().cloned().collect();
+        let (insert, args, _) = Self::insert_statement(
+            R::TABLE,
+            &columns,
+            std::slice::from_ref(data),
+            column_types,
+            user_id,
+        );
+
+        let mut set_clauses = vec![
+            "updated_by = EXCLUDED.updated_by".to_string(),
+            "updated_at = NOW()".to_string(),
+        ];
+        set_clauses.extend(
+            columns
+                .iter()
+                .filter(|column| *column != key && updatable.contains(&column.as_str()))
+                .map(|column| format!("{} = EXCLUDED.{}", column, column)),
+        );
+
+        let live = if R::SOFT_DELETE {
+            let column = format!("{}.deleted_at", R::TABLE);
+            Trashed::Without
+                .condition(&column)
+                .map(|condition| format!(" WHERE {}", condition))
+                .unwrap_or_default()
+        } else {
+            String::new()
+        };
+
+        let query = format!(
+            "{} ON CONFLICT ({}) DO UPDATE SET {}{} RETURNING *, (xmax = 0) AS inserted",
+            insert,
+            key,
+            set_clauses.join(", "),
+            live
+        );
+
+        (query, args)
+    }
+
+    // `UPDATE ... RETURNING *` of one live row of `R`, setting every non-protected
+    // key of `data`; with `versions`, only when its `updated_at` is one of them
+    fn update_statement<R: Resource>(
+        data: Map<String, Value>,
+        column_types: &TableColumns,
+        user_id: Uuid,
+        id: Uuid,
+        versions: Option<&[DateTime<Utc>]>,
+    ) -> (String, PgArguments) {
+        let mut set_clauses = Vec::new();
+        let mut args = PgArguments::default();
+
+        // Add standard fields
+        set_clauses.push("updated_by = $1".to_string());
+        let _ = args.add(user_id);
+
+        set_clauses.push("updated_at = NOW()".to_string());
+
+        // Add dynamic fields
+        let mut param_count = 2;
+        for (key, value) in data {
+            if let Some(column_type) = column_types.get(&key) {
+                let placeholder = column_type.bind(value, &mut args, param_count);
+                set_clauses.push(format!("{} = {}", key, placeholder));
+                param_count += 1;
+            }
+        }
+
+        // Add ID for WHERE clause
+        let _ = args.add(id);
+        let mut conditions = Self::live_row::<R>(&format!("${}", param_count));
+
+        if let Some(versions) = versions {
+            let _ = args.add(versions.to_vec());
+            conditions.push(format!("updated_at = ANY(${})", param_count + 1));
+        }
+
+        let query = format!(
+            "UPDATE {} SET {} WHERE {} RETURNING *",
+            R::TABLE,
+            set_clauses.join(", "),
+            conditions.join(" AND ")
+        );
+
+        (query, args)
+    }
+
+    // Convert a returned row to T through its JSON representation
+    fn row_to_item<T: DeserializeOwned>(row: &sqlx::postgres::PgRow) -> Result<T, AppError> {
+        let mut json_obj = serde_json::Map::new();
+        for column in row.columns() {
+            let column_name = column.name();
+            let value = Self::row_value_to_json(row, column_name);
+            json_obj.insert(column_name.to_string(), value);
+        }
+
+        serde_json::from_value(serde_json::Value::Object(json_obj))
+            .map_err(|e| AppError::SerializationError(e.to_string()))
+    }
+
     // Helper function to convert row values to JSON
     fn row_value_to_json(row: &sqlx::postgres::PgRow, column_name: &str) -> serde_json::Value {
         let column = row
@@ -401,3 +1280,227 @@ impl CrudService {
         serde_json::Value::Null
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        handlers::{contacts::ContactResource, users::UserResource},
+        utils::column_types::ColumnType,
+    };
+    use serde_json::json;
+
+    fn object(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => panic!("expected an object"),
+        }
+    }
+
+    fn contact_columns() -> TableColumns {
+        TableColumns::from([
+            ("id".to_string(), ColumnType::new("uuid", 'U')),
+            (
+                "first_name".to_string(),
+                ColumnType::new("character varying(100)", 'S'),
+            ),
+            ("is_customer".to_string(), ColumnType::new("boolean", 'B')),
+            (
+                "credit_limit".to_string(),
+                ColumnType::new("numeric(12,2)", 'N'),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_insert_statement_fills_missing_columns_with_default() {
+        let columns = vec!["first_name".to_string(), "is_customer".to_string()];
+        let rows = vec![
+            object(json!({"first_name": "Jo", "is_customer": true})),
+            object(json!({"first_name": "Ann"})),
+        ];
+
+        let (query, _, ids) = CrudService::insert_statement(
+            "contacts",
+            &columns,
+            &rows,
+            &contact_columns(),
+            Uuid::nil(),
+        );
+        assert_eq!(ids.len(), 2);
+        assert_ne!(ids[0], ids[1]);
+
+        assert_eq!(
+            query,
+            "INSERT INTO contacts (id, created_by, updated_by, first_name, is_customer) \
+             VALUES ($1, $2, $3, $4::character varying(100), $5::boolean), \
+             ($6, $7, $8, $9::character varying(100), DEFAULT)"
+        );
+    }
+
+    #[test]
+    fn test_updates_skip_trashed_rows() {
+        let changes = object(json!({"first_name": "Jo"}));
+        let versions = [Utc::now()];
+
+        // A PATCH of a trashed contact matches no row, and then reads as missing
+        let (query, _) = CrudService::update_statement::<ContactResource>(
+            changes.clone(),
+            &contact_columns(),
+            Uuid::nil(),
+            Uuid::nil(),
+            Some(&versions),
+        );
+        assert_eq!(
+            query,
+            "UPDATE contacts SET updated_by = $1, updated_at = NOW(), \
+             first_name = $2::character varying(100) \
+             WHERE contacts.id = $3 AND contacts.deleted_at IS NULL AND updated_at = ANY($4) \
+             RETURNING *"
+        );
+        assert_eq!(
+            CrudService::live_row::<ContactResource>("$1"),
+            vec!["contacts.id = $1", "contacts.deleted_at IS NULL"]
+        );
+
+        // Users are deleted for good, so there is nothing to skip
+        assert_eq!(
+            CrudService::live_row::<UserResource>("$1"),
+            vec!["users.id = $1"]
+        );
+    }
+
+    #[test]
+    fn test_sort_by_ids_restores_insert_order() {
+        let ids: Vec<Uuid> = (0..4).map(|_| Uuid::new_v4()).collect();
+        let mut returned = vec![Some(ids[2]), None, Some(ids[0]), Some(ids[3]), Some(ids[1])];
+
+        CrudService::sort_by_ids(&mut returned, &ids, |id| *id);
+        assert_eq!(
+            returned,
+            vec![Some(ids[0]), Some(ids[1]), Some(ids[2]), Some(ids[3]), None]
+        );
+    }
+
+    #[test]
+    fn test_upsert_statement_updates_only_updatable_columns() {
+        let mut columns = contact_columns();
+        columns.insert(
+            "code".to_string(),
+            ColumnType::new("character varying(20)", 'S'),
+        );
+        let data = object(json!({"code": "C-1", "credit_limit": 10, "first_name": "Jo"}));
+
+        let (query, _) = CrudService::upsert_statement::<ContactResource>(
+            "code",
+            &data,
+            &["first_name", "is_customer"],
+            &columns,
+            Uuid::nil(),
+        );
+
+        assert_eq!(
+            query,
+            "INSERT INTO contacts (id, created_by, updated_by, code, credit_limit, first_name) \
+             VALUES ($1, $2, $3, $4::character varying(20), $5::numeric(12,2), \
+             $6::character varying(100)) \
+             ON CONFLICT (code) DO UPDATE SET updated_by = EXCLUDED.updated_by, \
+             updated_at = NOW(), first_name = EXCLUDED.first_name \
+             WHERE contacts.deleted_at IS NULL \
+             RETURNING *, (xmax = 0) AS inserted"
+        );
+
+        // Users aren't soft-deleted, so any match is updated
+        let data = object(json!({"email": "jo@example.com", "first_name": "Jo"}));
+        let (query, _) = CrudService::upsert_statement::<UserResource>(
+            "email",
+            &data,
+            &["first_name"],
+            &columns,
+            Uuid::nil(),
+        );
+        assert!(query.ends_with(
+            "DO UPDATE SET updated_by = EXCLUDED.updated_by, updated_at = NOW(), \
+             first_name = EXCLUDED.first_name RETURNING *, (xmax = 0) AS inserted"
+        ));
+    }
+
+    #[test]
+    fn test_put_requires_every_update_field() {
+        let data = object(json!({"first_name": "Ada", "last_name": null}));
+        assert!(CrudService::missing_fields(&data, &["first_name", "last_name"]).is_empty());
+
+        let missing = CrudService::missing_fields(&data, &["first_name", "email", "phone"]);
+        let fields: Vec<&str> = missing.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["email", "phone"]);
+    }
+
+    #[test]
+    fn test_bulk_item_error_keeps_message_and_fields() {
+        let id = Uuid::nil();
+
+        let rejected = CrudService::bulk_item_error(
+            2,
+            Some(id),
+            AppError::BadRequest("email is taken".to_string()),
+        );
+        assert_eq!(
+            (rejected.index, rejected.id, rejected.error.as_str()),
+            (2, Some(id), "email is taken")
+        );
+
+        let missing = CrudService::bulk_item_error(0, Some(id), AppError::NotFound { id });
+        assert_eq!(
+            missing.error,
+            "Resource with id 00000000-0000-0000-0000-000000000000 not found"
+        );
+
+        let invalid = CrudService::bulk_item_error(
+            1,
+            None,
+            AppError::InvalidFields(vec![FieldError {
+                field: "code".to_string(),
+                error: "not a writable field".to_string(),
+            }]),
+        );
+        assert_eq!(invalid.error, "Invalid fields");
+        assert_eq!(invalid.fields.len(), 1);
+
+        // No Rust debug output in the response
+        let stale = CrudService::bulk_item_error(3, Some(id), AppError::PreconditionFailed);
+        assert_eq!(stale.error, "The resource was modified since it was read");
+    }
+
+    #[test]
+    fn test_field_errors_enforce_whitelist_and_column_types() {
+        let data = object(json!({
+            "id": "00000000-0000-0000-0000-000000000000",
+            "first_name": "Jo",
+            "is_customer": "yes",
+            "credit_limit": 1500.25,
+            "code": "C-1",
+            "nickname": "J"
+        }));
+        let writable = ["first_name", "is_customer", "credit_limit", "nickname"];
+
+        let mut errors: Vec<(String, String)> =
+            CrudService::field_errors(&data, &writable, &contact_columns())
+                .into_iter()
+                .map(|e| (e.field, e.error))
+                .collect();
+        errors.sort();
+
+        assert_eq!(
+            errors,
+            vec![
+                ("code".to_string(), "not a writable field".to_string()),
+                ("id".to_string(), "managed by the server".to_string()),
+                (
+                    "is_customer".to_string(),
+                    "expected a boolean for a boolean column".to_string()
+                ),
+                ("nickname".to_string(), "not a column".to_string()),
+            ]
+        );
+    }
+}
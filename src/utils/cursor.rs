@@ -0,0 +1,189 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::utils::{
+    query_builder::SortKey,
+    where_clause::{SqlValue, WhereClause},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CursorDirection {
+    Next,
+    Prev,
+}
+
+/// Opaque keyset pagination token.
+///
+/// Holds the sort key values (ending with `id`) of the row a page starts from and
+/// which way to walk from it. Clients only ever pass it back as `cursor=`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cursor {
+    #[serde(rename = "d")]
+    pub direction: CursorDirection,
+    #[serde(rename = "k")]
+    pub keys: Vec<String>,
+    #[serde(rename = "v")]
+    pub values: Vec<Value>,
+}
+
+impl Cursor {
+    /// Builds a cursor pointing at `row`, reading one value per sort key.
+    pub fn from_row(
+        direction: CursorDirection,
+        sort_keys: &[SortKey],
+        row: &Map<String, Value>,
+    ) -> Self {
+        Self {
+            direction,
+            keys: sort_keys
+                .iter()
+                .map(|key| key.column_name().to_string())
+                .collect(),
+            values: sort_keys
+                .iter()
+                .map(|key| row.get(key.column_name()).cloned().unwrap_or(Value::Null))
+                .collect(),
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(token: &str) -> Option<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(token).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    /// Whether this cursor was issued for the given sort order.
+    pub fn matches(&self, sort_keys: &[SortKey]) -> bool {
+        self.keys.len() == sort_keys.len()
+            && self.values.len() == sort_keys.len()
+            && self
+                .keys
+                .iter()
+                .zip(sort_keys)
+                .all(|(name, key)| name == key.column_name())
+    }
+
+    /// Compiles the condition selecting rows strictly after this cursor when ordered
+    /// by `sort_keys` (callers pass the reversed keys for `Prev` cursors).
+    ///
+    /// Expands the lexicographic comparison into `k1 > v1 OR (k1 = v1 AND k2 > v2) ...`
+    /// so mixed directions and NULL placement are honoured per column.
+    pub fn keyset_condition(&self, sort_keys: &[SortKey], clause: &mut WhereClause) -> String {
+        let mut branches = Vec::new();
+        let mut equal_prefix: Vec<String> = Vec::new();
+
+        for (index, (key, value)) in sort_keys.iter().zip(&self.values).enumerate() {
+            let value = clause.operand(&key.field, value);
+
+            if let Some(after) = after_condition(key, value.clone(), clause) {
+                let mut parts = equal_prefix.clone();
+                parts.push(after);
+                branches.push(if parts.len() == 1 {
+                    parts.remove(0)
+                } else {
+                    format!("({})", parts.join(" AND "))
+                });
+            }
+
+            // The last key never needs an equality branch
+            if index + 1 < sort_keys.len() {
+                equal_prefix.push(match value {
+                    Some(value) => format!("{} = {}", key.field, clause.bind(value)),
+                    None => format!("{} IS NULL", key.field),
+                });
+            }
+        }
+
+        if branches.is_empty() {
+            "FALSE".to_string()
+        } else {
+            format!("({})", branches.join(" OR "))
+        }
+    }
+}
+
+// Condition for `key` sorting strictly after `value`, or None when nothing can
+fn after_condition(
+    key: &SortKey,
+    value: Option<SqlValue>,
+    clause: &mut WhereClause,
+) -> Option<String> {
+    match value {
+        Some(value) => {
+            let op = if key.descending { "<" } else { ">" };
+            let comparison = format!("{} {} {}", key.field, op, clause.bind(value));
+            if key.nulls_first || !key.nullable {
+                Some(comparison)
+            } else {
+                Some(format!("({} OR {} IS NULL)", comparison, key.field))
+            }
+        }
+        None if key.nulls_first => Some(format!("{} IS NOT NULL", key.field)),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn keys() -> Vec<SortKey> {
+        vec![
+            SortKey::parse("-company").unwrap(),
+            SortKey::not_null("contacts.id", true),
+        ]
+    }
+
+    fn row(company: Value) -> Map<String, Value> {
+        json!({"id": "00000000-0000-0000-0000-000000000001", "company": company})
+            .as_object()
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn test_encode_round_trip_and_matches() {
+        let cursor = Cursor::from_row(CursorDirection::Next, &keys(), &row(json!("Acme")));
+        assert_eq!(cursor.keys, vec!["company", "id"]);
+
+        let decoded = Cursor::decode(&cursor.encode()).unwrap();
+        assert_eq!(decoded, cursor);
+        assert!(decoded.matches(&keys()));
+        assert!(!decoded.matches(&keys()[1..]));
+        assert_eq!(Cursor::decode("not a cursor"), None);
+    }
+
+    #[test]
+    fn test_keyset_condition() {
+        let cursor = Cursor::from_row(CursorDirection::Next, &keys(), &row(json!("Acme")));
+        let mut clause = WhereClause::new();
+        assert_eq!(
+            cursor.keyset_condition(&keys(), &mut clause),
+            "(company < $1 OR (company = $2 AND contacts.id < $3))"
+        );
+        assert_eq!(clause.params()[0], SqlValue::Text("Acme".to_string()));
+
+        // NULLs sort first when descending, so every other company follows a NULL one
+        let cursor = Cursor::from_row(CursorDirection::Next, &keys(), &row(Value::Null));
+        let mut clause = WhereClause::new();
+        assert_eq!(
+            cursor.keyset_condition(&keys(), &mut clause),
+            "(company IS NOT NULL OR (company IS NULL AND contacts.id < $1))"
+        );
+
+        // Ascending, NULLs come last and may still follow a value
+        let ascending = vec![SortKey::new("company", false)];
+        let cursor = Cursor::from_row(CursorDirection::Next, &ascending, &row(json!("Acme")));
+        let mut clause = WhereClause::new();
+        assert_eq!(
+            cursor.keyset_condition(&ascending, &mut clause),
+            "((company > $1 OR company IS NULL))"
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use serde_json::{Map, Value};
+
+/// The form email addresses are stored and looked up in: trimmed and lowercase,
+/// so they match case-insensitively.
+pub fn normalize(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Normalizes the `email` member of a row's values, if it has one.
+pub fn normalize_member(values: &mut Map<String, Value>) {
+    if let Some(Value::String(email)) = values.get_mut("email") {
+        *email = normalize(email);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("  Jo.Doe@Example.COM\n"), "jo.doe@example.com");
+        assert_eq!(normalize("jo@example.com"), "jo@example.com");
+
+        let mut values = match json!({"email": " Jo@Example.com", "first_name": "Jo"}) {
+            Value::Object(values) => values,
+            _ => unreachable!(),
+        };
+        normalize_member(&mut values);
+        assert_eq!(
+            Value::Object(values),
+            json!({"email": "jo@example.com", "first_name": "Jo"})
+        );
+
+        // Clearing the address is left to the column's constraints
+        let mut values = match json!({"email": null}) {
+            Value::Object(values) => values,
+            _ => unreachable!(),
+        };
+        normalize_member(&mut values);
+        assert_eq!(values["email"], Value::Null);
+    }
+}
@@ -0,0 +1,157 @@
+use axum::http::{header, HeaderMap, HeaderValue};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+
+/// Strong entity tag of a row version, derived from its `updated_at`
+/// (microseconds since the epoch, as Postgres stores it), as sent in
+/// `representation` (see [`representation`]). Tags of a version in other
+/// representations differ by a suffix, since their bodies differ too.
+pub fn etag(updated_at: DateTime<Utc>, representation: &str) -> String {
+    let micros = updated_at.timestamp_micros();
+    if representation.is_empty() {
+        format!("\"{}\"", micros)
+    } else {
+        let digest = format!("{:x}", Sha256::digest(representation.as_bytes()));
+        format!("\"{}-{}\"", micros, &digest[..16])
+    }
+}
+
+/// Key of the fieldset shaping a row's body, in the order its fields are
+/// returned. Empty for the full row.
+pub fn representation(fields: Option<&[String]>) -> String {
+    match fields {
+        Some(fields) => format!("fields={}", fields.join(",")),
+        None => String::new(),
+    }
+}
+
+/// `ETag` response header for a row version in `representation`.
+pub fn etag_header(updated_at: DateTime<Utc>, representation: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(&etag(updated_at, representation)) {
+        headers.insert(header::ETAG, value);
+    }
+    headers
+}
+
+/// Row versions an `If-Match` header accepts, or `None` when the write is
+/// unconditional (no header, or `*`). Weak and unrecognised tags never match,
+/// so a header carrying only those yields an empty list.
+pub fn if_match(headers: &HeaderMap) -> Option<Vec<DateTime<Utc>>> {
+    let value = headers.get(header::IF_MATCH)?.to_str().unwrap_or_default();
+    if value.trim() == "*" {
+        return None;
+    }
+
+    Some(
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.starts_with("W/"))
+            .filter_map(version)
+            .collect(),
+    )
+}
+
+/// Whether an `If-None-Match` header matches `etag`, i.e. the client's copy is
+/// current. Uses weak comparison, as RFC 9110 requires for this header.
+pub fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    let Some(value) = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+
+    value.trim() == "*"
+        || value
+            .split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == etag)
+}
+
+// The `updated_at` an entity tag was derived from, in any representation
+fn version(tag: &str) -> Option<DateTime<Utc>> {
+    let opaque = tag.strip_prefix('"')?.strip_suffix('"')?;
+    let micros = match opaque.split_once('-') {
+        Some((micros, _)) => micros,
+        None => opaque,
+    };
+    DateTime::from_timestamp_micros(micros.parse().ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(name: header::HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_if_match_parses_strong_tags_only() {
+        let updated_at = DateTime::from_timestamp_micros(1_754_000_000_123_456).unwrap();
+        let tag = etag(updated_at, "");
+        assert_eq!(tag, "\"1754000000123456\"");
+
+        assert_eq!(if_match(&HeaderMap::new()), None);
+        assert_eq!(if_match(&headers(header::IF_MATCH, "*")), None);
+        assert_eq!(
+            if_match(&headers(header::IF_MATCH, &format!("\"1\", {}", tag))),
+            Some(vec![
+                DateTime::from_timestamp_micros(1).unwrap(),
+                updated_at
+            ])
+        );
+        assert_eq!(
+            if_match(&headers(header::IF_MATCH, &format!("W/{}, \"x\"", tag))),
+            Some(vec![])
+        );
+
+        // A tag of a sparse representation names the same version
+        let sparse = etag(updated_at, "fields=id");
+        assert_eq!(
+            if_match(&headers(header::IF_MATCH, &sparse)),
+            Some(vec![updated_at])
+        );
+    }
+
+    #[test]
+    fn test_representations_get_their_own_tags() {
+        let updated_at = DateTime::from_timestamp_micros(42).unwrap();
+        let fields = ["id".to_string(), "first_name".to_string()];
+        let reordered = ["first_name".to_string(), "id".to_string()];
+
+        assert_eq!(representation(None), "");
+        assert_eq!(representation(Some(&fields)), "fields=id,first_name");
+
+        let full = etag(updated_at, "");
+        let sparse = etag(updated_at, &representation(Some(&fields)));
+        assert!(sparse.starts_with("\"42-") && sparse.len() == 21);
+        assert_ne!(sparse, full);
+        // Fields come back in the order asked for, so the body differs
+        assert_ne!(sparse, etag(updated_at, &representation(Some(&reordered))));
+        assert!(!if_none_match(
+            &headers(header::IF_NONE_MATCH, &full),
+            &sparse
+        ));
+    }
+
+    #[test]
+    fn test_if_none_match_uses_weak_comparison() {
+        let tag = etag(DateTime::from_timestamp_micros(42).unwrap(), "");
+
+        assert!(!if_none_match(&HeaderMap::new(), &tag));
+        assert!(if_none_match(&headers(header::IF_NONE_MATCH, "*"), &tag));
+        assert!(if_none_match(
+            &headers(header::IF_NONE_MATCH, "\"1\", W/\"42\""),
+            &tag
+        ));
+        assert!(!if_none_match(
+            &headers(header::IF_NONE_MATCH, "\"43\""),
+            &tag
+        ));
+    }
+}
@@ -0,0 +1,116 @@
+use serde::{ser::Error, ser::SerializeMap, Serialize, Serializer};
+use serde_json::Value;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Name of the result column produced by a SELECT entry,
+/// e.g. `contacts.id` -> `id`, `created_user.first_name as created_user_name` -> `created_user_name`.
+pub fn column_name(select_field: &str) -> &str {
+    let lower = select_field.to_ascii_lowercase();
+    match lower.rfind(" as ") {
+        Some(index) => select_field[index + 4..].trim(),
+        None => select_field
+            .rsplit('.')
+            .next()
+            .unwrap_or(select_field)
+            .trim(),
+    }
+}
+
+/// Resolves a `fields=` parameter against the resource's SELECT list.
+///
+/// Returns `None` when no fieldset was requested (or `*` is selected), meaning every
+/// column is returned. Unknown fields are logged and dropped.
+pub fn resolve_fields(requested: Option<&str>, select_fields: &[String]) -> Option<Arc<[String]>> {
+    let requested = requested.filter(|fields| !fields.trim().is_empty())?;
+    if select_fields.iter().any(|field| field == "*") {
+        return None;
+    }
+
+    let mut fields: Vec<String> = Vec::new();
+    for field in requested.split(',').map(str::trim) {
+        if select_fields
+            .iter()
+            .any(|select| column_name(select) == field)
+        {
+            if !fields.iter().any(|existing| existing == field) {
+                fields.push(field.to_string());
+            }
+        } else {
+            warn!("Requested field '{}' is not selectable", field);
+        }
+    }
+
+    Some(fields.into())
+}
+
+/// A response item that serializes only the requested fields (all of them when
+/// `fields` is `None`), in the order they were requested.
+#[derive(Debug, Clone)]
+pub struct Sparse<T> {
+    pub item: T,
+    pub fields: Option<Arc<[String]>>,
+}
+
+impl<T> Sparse<T> {
+    pub fn new(item: T, fields: Option<Arc<[String]>>) -> Self {
+        Self { item, fields }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Sparse<U> {
+        Sparse {
+            item: f(self.item),
+            fields: self.fields,
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for Sparse<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Some(fields) = &self.fields else {
+            return self.item.serialize(serializer);
+        };
+
+        match serde_json::to_value(&self.item).map_err(S::Error::custom)? {
+            Value::Object(object) => {
+                let mut map = serializer.serialize_map(None)?;
+                for field in fields.iter() {
+                    if let Some(value) = object.get(field) {
+                        map.serialize_entry(field, value)?;
+                    }
+                }
+                map.end()
+            }
+            other => other.serialize(serializer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_column_name() {
+        assert_eq!(column_name("id"), "id");
+        assert_eq!(column_name("contacts.id"), "id");
+        assert_eq!(
+            column_name("created_user.first_name AS created_user_name"),
+            "created_user_name"
+        );
+    }
+
+    #[test]
+    fn test_sparse_serializes_requested_fields_in_order() {
+        let select: Vec<String> = vec!["id".into(), "first_name".into(), "email".into()];
+        let fields = resolve_fields(Some("email, id,secret,email"), &select);
+        let item = json!({"id": 1, "first_name": "Jane", "email": "jane@example.com"});
+
+        let sparse = serde_json::to_string(&Sparse::new(item.clone(), fields)).unwrap();
+        assert_eq!(sparse, r#"{"email":"jane@example.com","id":1}"#);
+
+        let full = serde_json::to_value(Sparse::new(item.clone(), resolve_fields(None, &select)));
+        assert_eq!(full.unwrap(), item);
+    }
+}
@@ -0,0 +1,491 @@
+use serde_json::{Map, Value};
+use tracing::warn;
+
+use crate::utils::where_clause::{SqlValue, WhereClause};
+
+/// Operators accepted inside a field filter object,
+/// e.g. `{"created_at": {"gte": "2025-01-01"}}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOperator {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    In,
+    NotIn,
+    Between,
+    Like,
+    ILike,
+    IsNull,
+}
+
+impl FilterOperator {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "eq" => Some(FilterOperator::Eq),
+            "ne" => Some(FilterOperator::Ne),
+            "gt" => Some(FilterOperator::Gt),
+            "gte" => Some(FilterOperator::Gte),
+            "lt" => Some(FilterOperator::Lt),
+            "lte" => Some(FilterOperator::Lte),
+            "in" => Some(FilterOperator::In),
+            "not_in" => Some(FilterOperator::NotIn),
+            "between" => Some(FilterOperator::Between),
+            "like" => Some(FilterOperator::Like),
+            "ilike" => Some(FilterOperator::ILike),
+            "is_null" => Some(FilterOperator::IsNull),
+            _ => None,
+        }
+    }
+}
+
+/// Maximum nesting of `and`/`or`/`not` groups accepted in a filter.
+const MAX_FILTER_DEPTH: usize = 8;
+
+/// Compiles a filter object into top-level conditions that the caller ANDs together.
+///
+/// Keys are either field names (checked against `filterable_fields`) or the logical
+/// groups `and`/`or`, which take an array of filter objects, and `not`, which takes a
+/// single filter object. Sibling keys inside any object are ANDed, e.g.
+/// `{"or":[{"is_customer":true},{"and":[{"is_supplier":true},{"country":"Indonesia"}]}]}`.
+pub fn compile_filter(
+    filter: &Map<String, Value>,
+    filterable_fields: &[String],
+    clause: &mut WhereClause,
+) -> Vec<String> {
+    compile_object(filter, filterable_fields, clause, 0)
+}
+
+fn compile_object(
+    filter: &Map<String, Value>,
+    filterable_fields: &[String],
+    clause: &mut WhereClause,
+    depth: usize,
+) -> Vec<String> {
+    let mut conditions = Vec::new();
+
+    for (key, value) in filter {
+        match key.as_str() {
+            "and" | "or" | "not" if depth >= MAX_FILTER_DEPTH => {
+                warn!(
+                    "Filter groups nested deeper than {} are ignored",
+                    MAX_FILTER_DEPTH
+                );
+            }
+            "and" | "or" => {
+                let joiner = if key == "and" { " AND " } else { " OR " };
+                let Some(items) = value.as_array() else {
+                    warn!("Filter group '{}' needs an array of filter objects", key);
+                    continue;
+                };
+                let children: Vec<String> = items
+                    .iter()
+                    .filter_map(|item| {
+                        let Some(object) = item.as_object() else {
+                            warn!("Filter group '{}' contains a non-object entry", key);
+                            return None;
+                        };
+                        let child = compile_object(object, filterable_fields, clause, depth + 1);
+                        join_conditions(child, " AND ")
+                    })
+                    .collect();
+                if let Some(group) = join_conditions(children, joiner) {
+                    conditions.push(group);
+                }
+            }
+            "not" => {
+                let Some(object) = value.as_object() else {
+                    warn!("Filter group 'not' needs a filter object");
+                    continue;
+                };
+                let child = compile_object(object, filterable_fields, clause, depth + 1);
+                if let Some(inner) = join_conditions(child, " AND ") {
+                    conditions.push(format!("NOT {}", wrap(inner)));
+                }
+            }
+            field if filterable_fields.iter().any(|f| f == field) => {
+                conditions.extend(compile_field_filter(field, value, clause));
+            }
+            field => warn!("Filter field '{}' is not allowed: {:?}", field, value),
+        }
+    }
+
+    conditions
+}
+
+// Join conditions into one parenthesized expression; a single condition is returned as is
+fn join_conditions(conditions: Vec<String>, joiner: &str) -> Option<String> {
+    match conditions.len() {
+        0 => None,
+        1 => conditions.into_iter().next(),
+        _ => Some(format!("({})", conditions.join(joiner))),
+    }
+}
+
+fn wrap(condition: String) -> String {
+    if condition.starts_with('(') {
+        condition
+    } else {
+        format!("({})", condition)
+    }
+}
+
+/// Compiles an `exclude` entry for a single (already whitelisted) field.
+///
+/// A scalar drops rows equal to it, an array drops rows matching any element and
+/// `null` drops rows where the field is NULL. Rows whose field is NULL are kept
+/// when excluding values, so "everything except" really means everything else.
+pub fn compile_exclusion(field: &str, value: &Value, clause: &mut WhereClause) -> Option<String> {
+    match value {
+        Value::Null => Some(format!("{} IS NOT NULL", field)),
+        Value::Array(_) => {
+            let items = array_operand(field, FilterOperator::NotIn, value, clause)?;
+            if items.is_empty() {
+                return None;
+            }
+            let placeholders: Vec<String> =
+                items.into_iter().map(|value| clause.bind(value)).collect();
+            Some(format!(
+                "({} IS NULL OR {} NOT IN ({}))",
+                field,
+                field,
+                placeholders.join(", ")
+            ))
+        }
+        _ => {
+            let value = scalar_operand(field, FilterOperator::Ne, value, clause)?;
+            Some(format!("{} IS DISTINCT FROM {}", field, clause.bind(value)))
+        }
+    }
+}
+
+/// Compiles the filter for a single (already whitelisted) field into SQL conditions.
+///
+/// The value can be a scalar (`field = $n`), an array (`field IN (...)`), `null`
+/// (`field IS NULL`) or an object of operators that are ANDed together.
+/// Every value is bound as a parameter; malformed operators are logged and skipped.
+pub fn compile_field_filter(field: &str, value: &Value, clause: &mut WhereClause) -> Vec<String> {
+    match value {
+        Value::Object(operators) => operators
+            .iter()
+            .filter_map(|(name, operand)| match FilterOperator::parse(name) {
+                Some(op) => compile_operator(field, op, operand, clause),
+                None => {
+                    warn!("Unknown filter operator '{}' on field '{}'", name, field);
+                    None
+                }
+            })
+            .collect(),
+        Value::Array(_) => compile_operator(field, FilterOperator::In, value, clause)
+            .into_iter()
+            .collect(),
+        _ => compile_operator(field, FilterOperator::Eq, value, clause)
+            .into_iter()
+            .collect(),
+    }
+}
+
+fn compile_operator(
+    field: &str,
+    op: FilterOperator,
+    operand: &Value,
+    clause: &mut WhereClause,
+) -> Option<String> {
+    let condition = match op {
+        FilterOperator::Eq | FilterOperator::Ne if operand.is_null() => {
+            let not = if op == FilterOperator::Ne { " NOT" } else { "" };
+            format!("{} IS{} NULL", field, not)
+        }
+        FilterOperator::Eq
+        | FilterOperator::Ne
+        | FilterOperator::Gt
+        | FilterOperator::Gte
+        | FilterOperator::Lt
+        | FilterOperator::Lte => {
+            let sql_op = match op {
+                FilterOperator::Eq => "=",
+                FilterOperator::Ne => "<>",
+                FilterOperator::Gt => ">",
+                FilterOperator::Gte => ">=",
+                FilterOperator::Lt => "<",
+                _ => "<=",
+            };
+            let value = scalar_operand(field, op, operand, clause)?;
+            format!("{} {} {}", field, sql_op, clause.bind(value))
+        }
+        FilterOperator::In | FilterOperator::NotIn => {
+            let items = array_operand(field, op, operand, clause)?;
+            if items.is_empty() {
+                // An empty IN list matches nothing, an empty NOT IN list matches everything
+                return Some(
+                    if op == FilterOperator::In {
+                        "FALSE"
+                    } else {
+                        "TRUE"
+                    }
+                    .to_string(),
+                );
+            }
+            let placeholders: Vec<String> =
+                items.into_iter().map(|value| clause.bind(value)).collect();
+            let not = if op == FilterOperator::NotIn {
+                " NOT"
+            } else {
+                ""
+            };
+            format!("{}{} IN ({})", field, not, placeholders.join(", "))
+        }
+        FilterOperator::Between => {
+            let mut items = array_operand(field, op, operand, clause)?;
+            if items.len() != 2 {
+                warn!("Filter 'between' on '{}' needs exactly two values", field);
+                return None;
+            }
+            let upper = items.pop()?;
+            let lower = items.pop()?;
+            format!(
+                "{} BETWEEN {} AND {}",
+                field,
+                clause.bind(lower),
+                clause.bind(upper)
+            )
+        }
+        FilterOperator::Like | FilterOperator::ILike => {
+            let Some(pattern) = operand.as_str() else {
+                warn!("Filter '{:?}' on '{}' needs a string pattern", op, field);
+                return None;
+            };
+            let sql_op = if op == FilterOperator::Like {
+                "LIKE"
+            } else {
+                "ILIKE"
+            };
+            let value = SqlValue::Text(pattern.to_string());
+            format!("{} {} {}", field, sql_op, clause.bind(value))
+        }
+        FilterOperator::IsNull => {
+            let Some(is_null) = operand.as_bool() else {
+                warn!("Filter 'is_null' on '{}' needs a boolean", field);
+                return None;
+            };
+            let not = if is_null { "" } else { " NOT" };
+            format!("{} IS{} NULL", field, not)
+        }
+    };
+
+    Some(condition)
+}
+
+fn scalar_operand(
+    field: &str,
+    op: FilterOperator,
+    operand: &Value,
+    clause: &WhereClause,
+) -> Option<SqlValue> {
+    let value = clause.operand(field, operand);
+    if value.is_none() {
+        warn!("Filter '{:?}' on '{}' needs a scalar value", op, field);
+    }
+    value
+}
+
+fn array_operand(
+    field: &str,
+    op: FilterOperator,
+    operand: &Value,
+    clause: &WhereClause,
+) -> Option<Vec<SqlValue>> {
+    let Some(items) = operand.as_array() else {
+        warn!("Filter '{:?}' on '{}' needs an array", op, field);
+        return None;
+    };
+    let values: Option<Vec<SqlValue>> = items
+        .iter()
+        .map(|item| clause.operand(field, item))
+        .collect();
+    if values.is_none() {
+        warn!(
+            "Filter '{:?}' on '{}' only accepts scalar values",
+            op, field
+        );
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::column_types::{ColumnType, TableColumns};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn compile(filter: Value) -> WhereClause {
+        let fields: Vec<String> = ["city", "score", "email", "is_customer"]
+            .iter()
+            .map(|field| field.to_string())
+            .collect();
+        let mut clause = WhereClause::new();
+        let conditions = compile_filter(filter.as_object().unwrap(), &fields, &mut clause);
+        clause.extend(conditions);
+        clause
+    }
+
+    fn text(s: &str) -> SqlValue {
+        SqlValue::Text(s.to_string())
+    }
+
+    #[test]
+    fn test_operator_names() {
+        assert_eq!(FilterOperator::parse("gte"), Some(FilterOperator::Gte));
+        assert_eq!(FilterOperator::parse("not_in"), Some(FilterOperator::NotIn));
+        assert_eq!(
+            FilterOperator::parse("is_null"),
+            Some(FilterOperator::IsNull)
+        );
+        assert_eq!(FilterOperator::parse("GTE"), None);
+        assert_eq!(FilterOperator::parse("regex"), None);
+    }
+
+    #[test]
+    fn test_field_filter_operators() {
+        let mut clause = WhereClause::new();
+        let conditions = compile_field_filter(
+            "score",
+            &json!({"gt": 1, "lte": 5, "ne": null, "not_in": [2, 3], "between": [1, 9]}),
+            &mut clause,
+        );
+        assert_eq!(
+            conditions,
+            vec![
+                "score BETWEEN $1 AND $2",
+                "score > $3",
+                "score <= $4",
+                "score IS NOT NULL",
+                "score NOT IN ($5, $6)",
+            ]
+        );
+        assert_eq!(
+            clause.params(),
+            &[
+                SqlValue::Int(1),
+                SqlValue::Int(9),
+                SqlValue::Int(1),
+                SqlValue::Int(5),
+                SqlValue::Int(2),
+                SqlValue::Int(3),
+            ]
+        );
+
+        let mut clause = WhereClause::new();
+        assert_eq!(
+            compile_field_filter("city", &json!({"in": []}), &mut clause),
+            vec!["FALSE"]
+        );
+        assert_eq!(
+            compile_field_filter(
+                "city",
+                &json!({"like": "Ja%", "is_null": true}),
+                &mut clause
+            ),
+            vec!["city IS NULL", "city LIKE $1"]
+        );
+        assert_eq!(clause.params(), &[text("Ja%")]);
+    }
+
+    #[test]
+    fn test_malformed_operators_are_skipped() {
+        let mut clause = WhereClause::new();
+        let conditions = compile_field_filter(
+            "score",
+            &json!({"between": [1], "in": 1, "gt": [1], "like": 1, "is_null": "yes", "regex": "a"}),
+            &mut clause,
+        );
+        assert!(conditions.is_empty());
+        assert!(clause.params().is_empty());
+    }
+
+    #[test]
+    fn test_groups_nest_and_respect_whitelist() {
+        let clause = compile(json!({
+            "or": [{"city": "Jakarta"}, {"and": [{"score": {"gte": 3}}, {"password_hash": "x"}]}],
+            "not": {"is_customer": true}
+        }));
+        assert_eq!(
+            clause.to_sql(),
+            " WHERE NOT (is_customer = $1) AND (city = $2 OR score >= $3)"
+        );
+        assert_eq!(
+            clause.params(),
+            &[SqlValue::Bool(true), text("Jakarta"), SqlValue::Int(3)]
+        );
+
+        // Malformed groups and groups left empty add nothing
+        let clause = compile(json!({"or": {"city": "A"}, "and": [1, {"secret": 1}], "not": []}));
+        assert_eq!(clause, WhereClause::new());
+    }
+
+    #[test]
+    fn test_groups_deeper_than_limit_are_ignored() {
+        let mut filter = json!({"city": "A"});
+        for _ in 0..=MAX_FILTER_DEPTH {
+            filter = json!({ "not": filter });
+        }
+        let clause = compile(filter);
+        assert_eq!(clause, WhereClause::new());
+    }
+
+    #[test]
+    fn test_exclusion() {
+        let mut clause = WhereClause::new();
+        assert_eq!(
+            compile_exclusion("city", &json!("Jakarta"), &mut clause),
+            Some("city IS DISTINCT FROM $1".to_string())
+        );
+        assert_eq!(
+            compile_exclusion("city", &json!(["A", "B"]), &mut clause),
+            Some("(city IS NULL OR city NOT IN ($2, $3))".to_string())
+        );
+        assert_eq!(
+            compile_exclusion("city", &json!(null), &mut clause),
+            Some("city IS NOT NULL".to_string())
+        );
+        assert_eq!(compile_exclusion("city", &json!([]), &mut clause), None);
+        assert_eq!(
+            compile_exclusion("city", &json!({"a": 1}), &mut clause),
+            None
+        );
+        assert_eq!(clause.params(), &[text("Jakarta"), text("A"), text("B")]);
+    }
+
+    #[test]
+    fn test_values_bound_as_column_type() {
+        let columns = TableColumns::from([
+            (
+                "code".to_string(),
+                ColumnType::new("character varying(20)", 'S'),
+            ),
+            ("score".to_string(), ColumnType::new("numeric(5,2)", 'N')),
+        ]);
+        let mut clause = WhereClause::with_columns("contacts", Arc::new(columns));
+
+        let id = uuid::Uuid::nil().to_string();
+        assert_eq!(
+            compile_field_filter("code", &json!({"in": [id, "2025-01-01"]}), &mut clause),
+            vec!["code IN ($1::character varying, $2::character varying)"]
+        );
+        assert_eq!(
+            compile_field_filter("score", &json!({"between": [1, "2.5"]}), &mut clause),
+            vec!["score BETWEEN $3::numeric AND $4::numeric"]
+        );
+        assert_eq!(
+            clause.params()[0],
+            SqlValue::Cast(id, "character varying".to_string())
+        );
+        assert_eq!(
+            clause.params()[2],
+            SqlValue::Cast("1".to_string(), "numeric".to_string())
+        );
+    }
+}
@@ -1,14 +1,27 @@
 use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{jwk::JwkSet, Validation};
 use serde::{Deserialize, Serialize};
-use std::env;
+use std::sync::OnceLock;
 use uuid::Uuid;
 
-use crate::AppError;
+use crate::{utils::jwt_keys::JwtKeys, AppError};
+
+const ISSUER: &str = "rust-base-api";
+/// Audience of email verification tokens. Access tokens have none, so neither
+/// kind passes for the other.
+const VERIFY_EMAIL_AUDIENCE: &str = "verify-email";
+
+/// Lifetime of an email verification link.
+pub const VERIFICATION_TOKEN_TTL_HOURS: i64 = 24;
+
+/// Lifetime of an access token. Kept short: clients renew it with their
+/// refresh token, and only refresh tokens can be revoked.
+pub const ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,      // Subject (user ID)
+    pub sid: String,      // Session ID, checked against revocations
     pub username: String, // Username
     pub email: String,    // Email
     pub iat: i64,         // Issued at
@@ -17,68 +30,133 @@ pub struct Claims {
 }
 
 impl Claims {
-    pub fn new(user_id: Uuid, username: String, email: String) -> Self {
+    pub fn new(user_id: Uuid, session_id: Uuid, username: String, email: String) -> Self {
         let now = Utc::now();
-        let expiry = now + Duration::hours(24); // Token valid for 24 hours
+        let expiry = now + Duration::minutes(ACCESS_TOKEN_TTL_MINUTES);
 
         Self {
             sub: user_id.to_string(),
+            sid: session_id.to_string(),
             username,
             email,
             iat: now.timestamp(),
             exp: expiry.timestamp(),
-            iss: "rust-base-api".to_string(),
+            iss: ISSUER.to_string(),
         }
     }
 
     pub fn user_id(&self) -> Result<Uuid, AppError> {
         Uuid::parse_str(&self.sub).map_err(|_| AppError::UnAuthorized)
     }
+
+    pub fn session_id(&self) -> Result<Uuid, AppError> {
+        Uuid::parse_str(&self.sid).map_err(|_| AppError::UnAuthorized)
+    }
+}
+
+/// Claims of an email verification token: `email` is verified for user `sub` if
+/// it is still their address.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerificationClaims {
+    pub sub: String,
+    pub email: String,
+    pub aud: String,
+    pub iat: i64,
+    pub exp: i64,
+    pub iss: String,
+}
+
+impl VerificationClaims {
+    pub fn new(user_id: Uuid, email: String) -> Self {
+        let now = Utc::now();
+        let expiry = now + Duration::hours(VERIFICATION_TOKEN_TTL_HOURS);
+
+        Self {
+            sub: user_id.to_string(),
+            email,
+            aud: VERIFY_EMAIL_AUDIENCE.to_string(),
+            iat: now.timestamp(),
+            exp: expiry.timestamp(),
+            iss: ISSUER.to_string(),
+        }
+    }
+
+    pub fn user_id(&self) -> Result<Uuid, AppError> {
+        Uuid::parse_str(&self.sub).map_err(|_| Self::invalid())
+    }
+
+    fn invalid() -> AppError {
+        AppError::BadRequest("Invalid or expired verification token".to_string())
+    }
 }
 
+/// Signs and validates access tokens with the [`JwtKeys`] loaded at startup.
 pub struct JwtService;
 
+static KEYS: OnceLock<JwtKeys> = OnceLock::new();
+
 impl JwtService {
-    fn get_secret() -> String {
-        env::var("JWT_SECRET")
-            .unwrap_or_else(|_| "your-super-secret-jwt-key-change-this-in-production".to_string())
+    /// Installs the keys tokens are signed with; the first call wins.
+    pub fn init(keys: JwtKeys) {
+        if KEYS.set(keys).is_err() {
+            tracing::warn!("JWT keys are already loaded");
+        }
+    }
+
+    // Keys from `init`, or the environment's when it wasn't called (tests)
+    fn keys() -> &'static JwtKeys {
+        KEYS.get_or_init(|| JwtKeys::from_env().expect("Invalid JWT keys"))
+    }
+
+    /// Public keys other services verify tokens with.
+    pub fn jwks() -> JwkSet {
+        Self::keys().jwks()
     }
 
     pub fn generate_token(
         user_id: Uuid,
+        session_id: Uuid,
         username: String,
         email: String,
     ) -> Result<String, AppError> {
-        let claims = Claims::new(user_id, username, email);
-        let secret = Self::get_secret();
+        let claims = Claims::new(user_id, session_id, username, email);
 
-        encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(secret.as_ref()),
-        )
-        .map_err(|e| {
+        Self::keys().sign(&claims).map_err(|e| {
             tracing::error!("Failed to generate JWT token: {}", e);
             AppError::InternalServerError("Failed to generate authentication token".to_string())
         })
     }
 
     pub fn validate_token(token: &str) -> Result<Claims, AppError> {
-        let secret = Self::get_secret();
-        let mut validation = Validation::new(Algorithm::HS256);
-        validation.set_issuer(&["rust-base-api"]);
-
-        decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(secret.as_ref()),
-            &validation,
-        )
-        .map(|data| data.claims)
-        .map_err(|e| {
+        // The algorithm comes from the key the `kid` header names
+        let mut validation = Validation::default();
+        validation.set_issuer(&[ISSUER]);
+
+        Self::keys().verify(token, &validation).map_err(|e| {
             tracing::info!("JWT validation failed: {}", e);
             AppError::UnAuthorized
         })
     }
+
+    pub fn generate_verification_token(user_id: Uuid, email: String) -> Result<String, AppError> {
+        Self::keys()
+            .sign(&VerificationClaims::new(user_id, email))
+            .map_err(|e| {
+                tracing::error!("Failed to generate verification token: {}", e);
+                AppError::InternalServerError("Failed to generate verification token".to_string())
+            })
+    }
+
+    pub fn validate_verification_token(token: &str) -> Result<VerificationClaims, AppError> {
+        let mut validation = Validation::default();
+        validation.set_issuer(&[ISSUER]);
+        validation.set_audience(&[VERIFY_EMAIL_AUDIENCE]);
+
+        Self::keys().verify(token, &validation).map_err(|e| {
+            tracing::info!("Verification token rejected: {}", e);
+            VerificationClaims::invalid()
+        })
+    }
 }
 
 #[cfg(test)]
@@ -88,21 +166,46 @@ mod tests {
     #[test]
     fn test_jwt_token_generation_and_validation() {
         let user_id = Uuid::new_v4();
+        let session_id = Uuid::new_v4();
         let username = "testuser".to_string();
         let email = "test@example.com".to_string();
 
         // Generate token
-        let token = JwtService::generate_token(user_id, username.clone(), email.clone()).unwrap();
+        let token =
+            JwtService::generate_token(user_id, session_id, username.clone(), email.clone())
+                .unwrap();
         assert!(!token.is_empty());
 
         // Validate token
         let claims = JwtService::validate_token(&token).unwrap();
         assert_eq!(claims.sub, user_id.to_string());
+        assert_eq!(claims.session_id().unwrap(), session_id);
         assert_eq!(claims.username, username);
         assert_eq!(claims.email, email);
         assert_eq!(claims.iss, "rust-base-api");
     }
 
+    #[test]
+    fn test_verification_token_is_not_an_access_token() {
+        let user_id = Uuid::new_v4();
+        let token =
+            JwtService::generate_verification_token(user_id, "jo@example.com".to_string()).unwrap();
+
+        let claims = JwtService::validate_verification_token(&token).unwrap();
+        assert_eq!(claims.user_id().unwrap(), user_id);
+        assert_eq!(claims.email, "jo@example.com");
+        assert!(JwtService::validate_token(&token).is_err());
+
+        let access = JwtService::generate_token(
+            user_id,
+            Uuid::new_v4(),
+            "jo".into(),
+            "jo@example.com".into(),
+        )
+        .unwrap();
+        assert!(JwtService::validate_verification_token(&access).is_err());
+    }
+
     #[test]
     fn test_invalid_token() {
         let result = JwtService::validate_token("invalid.token.here");
@@ -1,8 +1,28 @@
+pub mod aggregate;
+pub mod code_sequence;
+pub mod column_types;
 pub mod constants;
 pub mod crud_service;
+pub mod cursor;
+pub mod email;
+pub mod etag;
+pub mod fieldset;
+pub mod filter;
 pub mod jwt;
+pub mod jwt_keys;
+pub mod mailer;
+pub mod patch;
+pub mod permission;
 pub mod query_builder;
+pub mod refresh_token;
+pub mod resource;
+pub mod session;
+pub mod where_clause;
 
+pub use aggregate::AggregateResponse;
 pub use crud_service::*;
+pub use fieldset::Sparse;
 pub use jwt::*;
 pub use query_builder::*;
+pub use refresh_token::RefreshTokenService;
+pub use resource::{Resource, ResourceFields};
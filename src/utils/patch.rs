@@ -0,0 +1,176 @@
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::AppError;
+
+/// Media type selecting JSON Patch on `PATCH`; any other JSON body is a merge patch.
+pub const JSON_PATCH_CONTENT_TYPE: &str = "application/json-patch+json";
+
+/// Body of a `PATCH` request.
+#[derive(Debug)]
+pub enum Patch {
+    /// RFC 7386 JSON Merge Patch: every member present is written (`null` clears
+    /// the column), absent members are left alone
+    Merge(Value),
+    /// RFC 6902 JSON Patch, applied to the current row
+    Json(Vec<PatchOperation>),
+}
+
+/// One RFC 6902 operation. Rows are flat, so paths address top-level members
+/// only (`/first_name`).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+/// Columns a merge patch writes: the members of the patch object.
+pub fn merge_patch_changes(patch: Value) -> Result<Map<String, Value>, AppError> {
+    match patch {
+        Value::Object(changes) => Ok(changes),
+        _ => Err(AppError::BadRequest(
+            "A merge patch must be a JSON object".to_string(),
+        )),
+    }
+}
+
+/// Columns a JSON Patch changes in `current`: every member whose value differs
+/// after applying `operations`, with removed members set to `null`.
+pub fn json_patch_changes(
+    current: Map<String, Value>,
+    operations: &[PatchOperation],
+) -> Result<Map<String, Value>, AppError> {
+    let mut patched = current.clone();
+    for (index, operation) in operations.iter().enumerate() {
+        apply(&mut patched, operation)
+            .map_err(|e| AppError::BadRequest(format!("Patch operation {}: {}", index, e)))?;
+    }
+
+    let mut changes = Map::new();
+    for (key, value) in &patched {
+        if current.get(key) != Some(value) {
+            changes.insert(key.clone(), value.clone());
+        }
+    }
+    for key in current.keys() {
+        if !patched.contains_key(key) {
+            changes.insert(key.clone(), Value::Null);
+        }
+    }
+    Ok(changes)
+}
+
+fn apply(document: &mut Map<String, Value>, operation: &PatchOperation) -> Result<(), String> {
+    match operation {
+        PatchOperation::Add { path, value } => {
+            document.insert(member(path)?, value.clone());
+        }
+        PatchOperation::Remove { path } => {
+            take(document, path)?;
+        }
+        PatchOperation::Replace { path, value } => {
+            let key = member(path)?;
+            match document.get_mut(&key) {
+                Some(existing) => *existing = value.clone(),
+                None => return Err(format!("path {} does not exist", path)),
+            }
+        }
+        PatchOperation::Move { from, path } => {
+            let value = take(document, from)?;
+            document.insert(member(path)?, value);
+        }
+        PatchOperation::Copy { from, path } => {
+            let value = document
+                .get(&member(from)?)
+                .cloned()
+                .ok_or_else(|| format!("path {} does not exist", from))?;
+            document.insert(member(path)?, value);
+        }
+        PatchOperation::Test { path, value } => {
+            if document.get(&member(path)?) != Some(value) {
+                return Err(format!("test failed at {}", path));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn take(document: &mut Map<String, Value>, path: &str) -> Result<Value, String> {
+    document
+        .remove(&member(path)?)
+        .ok_or_else(|| format!("path {} does not exist", path))
+}
+
+// Member name addressed by a top-level JSON Pointer, e.g. `/first_name`
+fn member(path: &str) -> Result<String, String> {
+    match path.strip_prefix('/') {
+        Some(name) if !name.is_empty() && !name.contains('/') => {
+            Ok(name.replace("~1", "/").replace("~0", "~"))
+        }
+        _ => Err(format!("unsupported path {}", path)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn object(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => panic!("expected an object"),
+        }
+    }
+
+    #[test]
+    fn test_merge_patch_writes_only_present_members() {
+        let changes = merge_patch_changes(json!({"email": null, "is_customer": true})).unwrap();
+        assert_eq!(
+            Value::Object(changes),
+            json!({"email": null, "is_customer": true})
+        );
+
+        assert!(merge_patch_changes(json!([1, 2])).is_err());
+    }
+
+    #[test]
+    fn test_json_patch_diffs_against_current_row() {
+        let current = object(json!({
+            "id": "1",
+            "first_name": "Jo",
+            "email": "jo@example.com",
+            "is_customer": false
+        }));
+        let operations: Vec<PatchOperation> = serde_json::from_value(json!([
+            {"op": "test", "path": "/first_name", "value": "Jo"},
+            {"op": "replace", "path": "/first_name", "value": "Joanna"},
+            {"op": "remove", "path": "/email"},
+            {"op": "replace", "path": "/is_customer", "value": false}
+        ]))
+        .unwrap();
+
+        let changes = json_patch_changes(current.clone(), &operations).unwrap();
+        assert_eq!(
+            Value::Object(changes),
+            json!({"first_name": "Joanna", "email": null})
+        );
+
+        let failing: Vec<PatchOperation> = serde_json::from_value(json!([
+            {"op": "test", "path": "/first_name", "value": "Ann"}
+        ]))
+        .unwrap();
+        assert!(json_patch_changes(current.clone(), &failing).is_err());
+
+        let nested: Vec<PatchOperation> = serde_json::from_value(json!([
+            {"op": "add", "path": "/address/city", "value": "Bandung"}
+        ]))
+        .unwrap();
+        assert!(json_patch_changes(current, &nested).is_err());
+    }
+}
@@ -0,0 +1,53 @@
+use axum::http::Method;
+
+/// Grants every permission.
+pub const ALL: &str = "*";
+
+/// Permission name for `action` on `resource`, e.g. `contacts:write`.
+pub fn permission(resource: &str, action: &str) -> String {
+    format!("{}:{}", resource, action)
+}
+
+/// Action a request method performs on a resource: `read` for safe methods,
+/// `delete` for DELETE and `write` for everything else.
+pub fn action(method: &Method) -> &'static str {
+    match *method {
+        Method::GET | Method::HEAD | Method::OPTIONS => "read",
+        Method::DELETE => "delete",
+        _ => "write",
+    }
+}
+
+/// Whether holding `granted` satisfies `required`. `*` grants everything and
+/// `contacts:*` every action on contacts.
+pub fn grants(granted: &str, required: &str) -> bool {
+    if granted == ALL || granted == required {
+        return true;
+    }
+    match (granted.strip_suffix(":*"), required.split_once(':')) {
+        (Some(resource), Some((required_resource, _))) => resource == required_resource,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grants_matches_exact_and_wildcard_permissions() {
+        assert!(grants("contacts:write", "contacts:write"));
+        assert!(!grants("contacts:read", "contacts:write"));
+        assert!(grants("contacts:*", "contacts:purge"));
+        assert!(!grants("contacts:*", "users:read"));
+        assert!(!grants("contacts", "contacts:read"));
+        assert!(grants("*", "code_sequences:write"));
+    }
+
+    #[test]
+    fn test_action_follows_request_method() {
+        assert_eq!(permission("users", action(&Method::GET)), "users:read");
+        assert_eq!(action(&Method::PATCH), "write");
+        assert_eq!(action(&Method::DELETE), "delete");
+    }
+}
@@ -1,10 +1,22 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{postgres::PgArguments, Arguments, Column, PgPool, Row};
-use std::collections::HashMap;
+use sqlx::{postgres::PgArguments, Column, PgPool, Row};
+use std::{collections::HashMap, sync::Arc};
 use tracing::{debug, warn};
 use uuid::Uuid; // Add tracing for logging
 
+use crate::{
+    utils::{
+        aggregate::{Aggregate, AggregateResponse, GroupBy},
+        column_types::{self, TableColumns},
+        cursor::{Cursor, CursorDirection},
+        fieldset::{column_name, resolve_fields, Sparse},
+        filter::{compile_exclusion, compile_filter},
+        where_clause::{into_arguments, SqlValue, WhereClause},
+    },
+    FieldError,
+};
+
 #[derive(Debug, Deserialize)]
 pub struct QueryParams {
     pub page: Option<u32>,
@@ -13,8 +25,10 @@ pub struct QueryParams {
     pub search: Option<String>,
     pub filter: Option<String>,  // JSON string
     pub include: Option<String>, // comma-separated relation names
-    #[allow(dead_code)]
-    pub exclude: Option<String>, // JSON array
+    pub exclude: Option<String>, // JSON object of field -> value(s) to leave out
+    // Multi-column sort, e.g. `sort=-company,last_name:nulls_first`;
+    // takes precedence over sortBy/sortOrder
+    pub sort: Option<String>,
     #[serde(rename = "sortBy")]
     pub sort_by: Option<String>,
     #[serde(rename = "sortOrder")]
@@ -24,6 +38,25 @@ pub struct QueryParams {
     // Format: search_fields=field1,field2,field3&search_value=searchterm
     pub search_fields: Option<String>, // comma-separated field names
     pub search_value: Option<String>,  // search term for the fields
+    // How the search term is matched: ilike (default), fts or fuzzy
+    pub search_mode: Option<String>,
+
+    // Sparse fieldset: comma-separated columns to return, e.g. `fields=id,first_name`
+    pub fields: Option<String>,
+
+    // Keyset pagination: `cursor=` (or `after=`) switches to cursor mode,
+    // an empty value starts at the first page
+    #[serde(alias = "after")]
+    pub cursor: Option<String>,
+    // Whether to run the COUNT(*) query (defaults to true, false in cursor mode)
+    pub with_count: Option<bool>,
+
+    // Aggregate endpoints: `group_by=country,created_at:month&agg=count,sum:amount`
+    pub group_by: Option<String>,
+    pub agg: Option<String>,
+
+    // Soft-deleted rows: `trashed=with` includes them, `trashed=only` lists just them
+    pub trashed: Option<String>,
 }
 
 impl Default for QueryParams {
@@ -35,17 +68,25 @@ impl Default for QueryParams {
             filter: None,
             include: None,
             exclude: None,
-            sort_by: Some("created_at".to_string()),
-            sort_order: Some("desc".to_string()),
+            sort: None,
+            sort_by: None,
+            sort_order: None,
             search_fields: None,
             search_value: None,
+            search_mode: None,
+            fields: None,
+            cursor: None,
+            with_count: None,
+            group_by: None,
+            agg: None,
+            trashed: None,
         }
     }
 }
 
 #[derive(Debug, Serialize)]
 pub struct PaginatedResponse<T> {
-    pub count: u64,
+    pub count: Option<u64>,
     pub page_context: PageContext,
     pub links: PaginationLinks,
     pub results: Vec<T>,
@@ -55,7 +96,7 @@ pub struct PaginatedResponse<T> {
 pub struct PageContext {
     pub page: u32,
     pub per_page: u32,
-    pub total_pages: u32,
+    pub total_pages: Option<u32>,
 }
 
 #[derive(Debug, Serialize)]
@@ -63,23 +104,283 @@ pub struct PaginationLinks {
     pub first: String,
     pub previous: Option<String>,
     pub next: Option<String>,
-    pub last: String,
+    pub last: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prev_cursor: Option<String>,
+}
+
+/// One column of the ORDER BY clause.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKey {
+    pub field: String,
+    pub descending: bool,
+    pub nulls_first: bool,
+    pub nullable: bool,
+}
+
+impl SortKey {
+    pub fn new(field: &str, descending: bool) -> Self {
+        Self {
+            field: field.to_string(),
+            descending,
+            // PostgreSQL puts NULLs last for ASC and first for DESC
+            nulls_first: descending,
+            nullable: true,
+        }
+    }
+
+    /// A NOT NULL column such as the primary key, used as a keyset tie-breaker.
+    pub fn not_null(field: &str, descending: bool) -> Self {
+        Self {
+            nullable: false,
+            ..Self::new(field, descending)
+        }
+    }
+
+    pub fn reversed(&self) -> Self {
+        Self {
+            field: self.field.clone(),
+            descending: !self.descending,
+            nulls_first: !self.nulls_first,
+            nullable: self.nullable,
+        }
+    }
+
+    /// Parses one `sort` entry: a field name, optionally prefixed with `-` (descending)
+    /// or `+` (ascending) and suffixed with `:nulls_first` or `:nulls_last`.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (field, nulls) = match spec.trim().split_once(':') {
+            Some((field, nulls)) => (field, Some(nulls)),
+            None => (spec.trim(), None),
+        };
+
+        let mut key = match field.strip_prefix('-') {
+            Some(field) => SortKey::new(field, true),
+            None => SortKey::new(field.strip_prefix('+').unwrap_or(field), false),
+        };
+        if key.field.is_empty() {
+            return None;
+        }
+
+        match nulls {
+            None => {}
+            Some("nulls_first") => key.nulls_first = true,
+            Some("nulls_last") => key.nulls_first = false,
+            Some(_) => return None,
+        }
+
+        Some(key)
+    }
+
+    /// Column name as it appears in result rows (without a table qualifier).
+    pub fn column_name(&self) -> &str {
+        self.field.rsplit('.').next().unwrap_or(&self.field)
+    }
+
+    pub fn to_sql(&self) -> String {
+        let direction = if self.descending { "DESC" } else { "ASC" };
+        if self.nulls_first == self.descending {
+            format!("{} {}", self.field, direction)
+        } else {
+            let nulls = if self.nulls_first { "FIRST" } else { "LAST" };
+            format!("{} {} NULLS {}", self.field, direction, nulls)
+        }
+    }
+}
+
+/// Text search configuration shared with the generated `search_vector` columns.
+const TEXT_SEARCH_CONFIG: &str = "simple";
+
+/// How the `search`/`search_value` term is matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    /// `field ILIKE '%term%'` ORed across the search fields
+    ILike,
+    /// Prefix-matching tsquery against the resource's tsvector column, ranked by `ts_rank`
+    FullText,
+    /// `pg_trgm` similarity across the search fields, ranked by the best match
+    Fuzzy,
+}
+
+impl SearchMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "ilike" => Some(SearchMode::ILike),
+            "fts" => Some(SearchMode::FullText),
+            "fuzzy" => Some(SearchMode::Fuzzy),
+            _ => None,
+        }
+    }
+}
+
+/// Which soft-deleted rows a query returns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Trashed {
+    /// Only rows that are not deleted
+    #[default]
+    Without,
+    /// Deleted and non-deleted rows
+    With,
+    /// Only deleted rows
+    Only,
+}
+
+impl Trashed {
+    /// The `trashed` query parameter; missing or unknown values hide deleted rows.
+    pub fn from_params(params: &QueryParams) -> Self {
+        match params.trashed.as_deref() {
+            None => Trashed::Without,
+            Some("with") => Trashed::With,
+            Some("only") => Trashed::Only,
+            Some(other) => {
+                warn!("Unknown trashed value ignored: {}", other);
+                Trashed::Without
+            }
+        }
+    }
+
+    /// Condition on the soft-delete `column` selecting these rows, if any.
+    pub fn condition(self, column: &str) -> Option<String> {
+        match self {
+            Trashed::Without => Some(format!("{} IS NULL", column)),
+            Trashed::With => None,
+            Trashed::Only => Some(format!("{} IS NOT NULL", column)),
+        }
+    }
+}
+
+/// Turns free text into a prefix-matching tsquery, e.g. `jo smi` -> `jo:* & smi:*`.
+/// Anything but letters and digits separates words, so user input can't inject
+/// tsquery operators.
+fn prefix_tsquery(search: &str) -> Option<String> {
+    let terms: Vec<String> = search
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("{}:*", word.to_lowercase()))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" & "))
 }
 
 pub struct QueryBuilder {
     table: String,
     select_fields: Vec<String>,
     search_fields: Vec<String>,
+    search_vector: Option<String>,
+    soft_delete: Option<String>,
     filterable_fields: Vec<String>,
     sortable_fields: Vec<String>,
+    default_sort: Vec<SortKey>,
     joins: Vec<String>,
     include_relations: HashMap<String, IncludeConfig>,
+    column_types: Option<Arc<TableColumns>>,
 }
 
+/// A relation that can be requested with `include=` and is returned as nested JSON
+/// under the include name. Each include is a correlated subquery aliased to that name,
+/// so it never multiplies rows or needs joining into the count query.
 #[derive(Clone)]
-pub struct IncludeConfig {
-    pub join_clause: String,
-    pub select_fields: Vec<String>,
+pub enum IncludeConfig {
+    /// Belongs-to: the `table` row whose `id` equals `{parent}.{local_key}`, as an object or null
+    One {
+        table: String,
+        local_key: String,
+        fields: Vec<String>,
+        soft_delete: Option<String>,
+    },
+    /// Has-many: the `table` rows whose `foreign_key` equals `{parent}.id`, as an array
+    Many {
+        table: String,
+        foreign_key: String,
+        fields: Vec<String>,
+        soft_delete: Option<String>,
+    },
+}
+
+impl IncludeConfig {
+    pub fn one(table: &str, local_key: &str, fields: Vec<&str>) -> Self {
+        IncludeConfig::One {
+            table: table.to_string(),
+            local_key: local_key.to_string(),
+            fields: fields.iter().map(|s| s.to_string()).collect(),
+            soft_delete: None,
+        }
+    }
+
+    pub fn many(table: &str, foreign_key: &str, fields: Vec<&str>) -> Self {
+        IncludeConfig::Many {
+            table: table.to_string(),
+            foreign_key: foreign_key.to_string(),
+            fields: fields.iter().map(|s| s.to_string()).collect(),
+            soft_delete: None,
+        }
+    }
+
+    /// Leaves out related rows soft-deleted through `column` (e.g. `deleted_at`).
+    pub fn without_trashed(mut self, column: &str) -> Self {
+        match &mut self {
+            IncludeConfig::One { soft_delete, .. } | IncludeConfig::Many { soft_delete, .. } => {
+                *soft_delete = Some(column.to_string())
+            }
+        }
+        self
+    }
+
+    /// SELECT entry producing the relation as JSON in a column called `name`.
+    pub fn select_expr(&self, name: &str, parent_table: &str) -> String {
+        match self {
+            IncludeConfig::One {
+                table,
+                local_key,
+                fields,
+                soft_delete,
+            } => format!(
+                "(SELECT {} FROM {} {} WHERE {}.id = {}.{}{}) AS {}",
+                json_object(name, fields),
+                table,
+                name,
+                name,
+                parent_table,
+                local_key,
+                not_trashed(name, soft_delete),
+                name
+            ),
+            IncludeConfig::Many {
+                table,
+                foreign_key,
+                fields,
+                soft_delete,
+            } => format!(
+                "(SELECT COALESCE(json_agg({}), '[]'::json) FROM {} {} WHERE {}.{} = {}.id{}) AS {}",
+                json_object(name, fields),
+                table,
+                name,
+                name,
+                foreign_key,
+                parent_table,
+                not_trashed(name, soft_delete),
+                name
+            ),
+        }
+    }
+}
+
+// ` AND alias.column IS NULL` for a soft-deleted relation, empty otherwise
+fn not_trashed(alias: &str, soft_delete: &Option<String>) -> String {
+    soft_delete
+        .as_ref()
+        .map(|column| format!(" AND {}.{} IS NULL", alias, column))
+        .unwrap_or_default()
+}
+
+// `json_build_object('id', alias.id, ...)` over the given columns of `alias`
+fn json_object(alias: &str, fields: &[String]) -> String {
+    let pairs: Vec<String> = fields
+        .iter()
+        .map(|field| format!("'{}', {}.{}", field, alias, field))
+        .collect();
+    format!("json_build_object({})", pairs.join(", "))
 }
 
 impl QueryBuilder {
@@ -88,10 +389,14 @@ impl QueryBuilder {
             table: table.to_string(),
             select_fields: vec!["*".to_string()],
             search_fields: Vec::new(),
+            search_vector: None,
+            soft_delete: None,
             filterable_fields: Vec::new(),
             sortable_fields: Vec::new(),
+            default_sort: vec![SortKey::not_null(&format!("{}.id", table), false)],
             joins: Vec::new(),
             include_relations: HashMap::new(),
+            column_types: None,
         }
     }
 
@@ -105,6 +410,19 @@ impl QueryBuilder {
         self
     }
 
+    /// Generated `tsvector` column that enables `search_mode=fts`.
+    pub fn full_text(mut self, column: &str) -> Self {
+        self.search_vector = Some(column.to_string());
+        self
+    }
+
+    /// Timestamp column marking soft-deleted rows (e.g. `deleted_at`). Rows where it
+    /// is set are left out unless the request asks for `trashed=with|only`.
+    pub fn soft_delete(mut self, column: &str) -> Self {
+        self.soft_delete = Some(column.to_string());
+        self
+    }
+
     pub fn filterable(mut self, fields: Vec<&str>) -> Self {
         self.filterable_fields = fields.iter().map(|s| s.to_string()).collect();
         self
@@ -115,36 +433,45 @@ impl QueryBuilder {
         self
     }
 
+    /// Sort used when the request names no valid sortable field, in `sort=` syntax
+    /// (e.g. `"-created_at"`). Defaults to the table's `id`.
+    pub fn default_sort(mut self, sort: &str) -> Self {
+        let keys: Vec<SortKey> = sort.split(',').filter_map(SortKey::parse).collect();
+        if !keys.is_empty() {
+            self.default_sort = keys;
+        }
+        self
+    }
+
     pub fn join(mut self, join_clause: &str) -> Self {
         self.joins.push(join_clause.to_string());
         self
     }
 
-    pub fn include_relation(
-        mut self,
-        name: &str,
-        join_clause: &str,
-        select_fields: Vec<&str>,
-    ) -> Self {
-        self.include_relations.insert(
-            name.to_string(),
-            IncludeConfig {
-                join_clause: join_clause.to_string(),
-                select_fields: select_fields.iter().map(|s| s.to_string()).collect(),
-            },
-        );
+    /// Registers a relation clients can request with `include={name}`.
+    pub fn include_relation(mut self, name: &str, config: IncludeConfig) -> Self {
+        self.include_relations.insert(name.to_string(), config);
+        self
+    }
+
+    /// Types of the table's columns. Filter, exclude and cursor values are then
+    /// bound as the type of the column they are compared with, instead of guessed.
+    pub fn column_types(mut self, columns: Arc<TableColumns>) -> Self {
+        self.column_types = Some(columns);
         self
     }
 
     fn generate_pagination_links(
         &self,
         page: u32,
-        total_pages: u32,
+        total_pages: Option<u32>,
         per_page: u32,
+        has_more: bool,
         base_url: &str,
     ) -> PaginationLinks {
         let first = format!("{}?page=1&perPage={}", base_url, per_page);
-        let last = format!("{}?page={}&perPage={}", base_url, total_pages, per_page);
+        let last = total_pages
+            .map(|total_pages| format!("{}?page={}&perPage={}", base_url, total_pages, per_page));
 
         let previous = if page > 1 {
             Some(format!(
@@ -157,7 +484,12 @@ impl QueryBuilder {
             None
         };
 
-        let next = if page < total_pages {
+        // Without a count, assume another page exists whenever this one is full
+        let has_next = match total_pages {
+            Some(total_pages) => page < total_pages,
+            None => has_more,
+        };
+        let next = if has_next {
             Some(format!(
                 "{}?page={}&perPage={}",
                 base_url,
@@ -173,357 +505,581 @@ impl QueryBuilder {
             previous,
             next,
             last,
+            next_cursor: None,
+            prev_cursor: None,
+        }
+    }
+
+    fn generate_cursor_links(
+        &self,
+        next_cursor: Option<String>,
+        prev_cursor: Option<String>,
+        per_page: u32,
+        base_url: &str,
+    ) -> PaginationLinks {
+        let link = |cursor: &String| format!("{}?cursor={}&perPage={}", base_url, cursor, per_page);
+
+        PaginationLinks {
+            first: format!("{}?cursor=&perPage={}", base_url, per_page),
+            previous: prev_cursor.as_ref().map(link),
+            next: next_cursor.as_ref().map(link),
+            last: None,
+            next_cursor,
+            prev_cursor,
         }
     }
 
     pub fn build_query(&self, params: &QueryParams) -> (String, PgArguments, u32, u32) {
-        let page = params.page.unwrap_or(1);
+        let (query, values, page, per_page) = self.select_sql(params);
+        (query, into_arguments(values), page, per_page)
+    }
+
+    pub fn build_count_query(&self, params: &QueryParams) -> (String, PgArguments) {
+        let (query, values) = self.count_sql(params);
+        (query, into_arguments(values))
+    }
+
+    /// SQL text and bind values of the row query.
+    pub fn select_sql(&self, params: &QueryParams) -> (String, Vec<SqlValue>, u32, u32) {
+        let page = params.page.unwrap_or(1).max(1);
         let per_page = params.per_page.unwrap_or(10).min(100); // Max 100 per page
         let offset = (page - 1) * per_page;
 
-        let mut query = format!(
-            "SELECT {} FROM {}",
-            self.select_fields.join(", "),
-            self.table
-        );
+        let sort_keys = self.sort_keys(params);
+        let mut select_fields = match resolve_fields(params.fields.as_deref(), &self.select_fields)
+        {
+            Some(fields) => self.sparse_select(&fields, &sort_keys),
+            None => self.select_fields.clone(),
+        };
+
+        // Add dynamic includes as nested JSON columns
+        for (name, config) in self.requested_includes(params) {
+            select_fields.push(config.select_expr(name, &self.table));
+        }
 
-        // Add static joins
+        let mut query = format!("SELECT {} FROM {}", select_fields.join(", "), self.table);
         for join in &self.joins {
             query.push_str(&format!(" {}", join));
         }
 
-        // Add dynamic includes
-        if let Some(includes) = &params.include {
-            for include_name in includes.split(',') {
-                let include_name = include_name.trim();
-                if let Some(config) = self.include_relations.get(include_name) {
-                    query.push_str(&format!(" {}", config.join_clause));
-
-                    // Add include fields to SELECT if not using *
-                    if !self.select_fields.contains(&"*".to_string())
-                        && !config.select_fields.is_empty()
-                    {
-                        query = query.replace(
-                            &format!("SELECT {}", self.select_fields.join(", ")),
-                            &format!(
-                                "SELECT {}, {}",
-                                self.select_fields.join(", "),
-                                config.select_fields.join(", ")
-                            ),
-                        );
-                    }
+        let mut clause = self.compile_where(params);
 
-                    debug!("Added include join for: {}", include_name);
-                }
+        // Keyset pagination: only rows after the cursor, walking backwards for `prev`
+        let cursor = self.decode_cursor(params, &sort_keys);
+        let order_keys: Vec<SortKey> = match &cursor {
+            Some(cursor) if cursor.direction == CursorDirection::Prev => {
+                sort_keys.iter().map(SortKey::reversed).collect()
             }
+            _ => sort_keys,
+        };
+        if let Some(cursor) = &cursor {
+            let condition = cursor.keyset_condition(&order_keys, &mut clause);
+            clause.push(condition);
         }
 
-        let mut conditions = Vec::new();
-        let mut args = PgArguments::default();
-        let mut param_count = 1;
+        query.push_str(&clause.to_sql());
 
-        // Search functionality with prepared statements
-        // Priority: dynamic search_fields > static search_fields
-        let search_term = params.search_value.as_ref().or(params.search.as_ref());
+        // Sorting, by search relevance first when ranking applies (cursors only
+        // encode the sort keys, so cursor mode never ranks)
+        let mut order_by: Vec<String> = Vec::new();
+        if params.cursor.is_none() {
+            order_by.extend(self.relevance_order(params, &mut clause));
+        }
+        order_by.extend(order_keys.iter().map(SortKey::to_sql));
+        query.push_str(&format!(" ORDER BY {}", order_by.join(", ")));
 
-        if let Some(search) = search_term {
-            if !search.is_empty() {
-                let search_pattern = format!("%{}%", search);
-
-                // Determine which fields to search in
-                let (fields_to_search, _invalid_fields) =
-                    if let Some(dynamic_fields) = &params.search_fields {
-                        // Use dynamic fields from URL parameter
-                        let requested_fields: Vec<String> = dynamic_fields
-                            .split(',')
-                            .map(|s| s.trim().to_string())
-                            .collect();
-
-                        let valid_fields: Vec<String> = requested_fields
-                            .iter()
-                            .filter(|field| self.search_fields.contains(field))
-                            .cloned()
-                            .collect();
-
-                        let invalid_fields: Vec<String> = requested_fields
-                            .iter()
-                            .filter(|field| !self.search_fields.contains(field))
-                            .cloned()
-                            .collect();
-
-                        debug!("Dynamic search - Requested fields: {:?}", requested_fields);
-                        debug!("Dynamic search - Valid fields: {:?}", valid_fields);
-
-                        if !invalid_fields.is_empty() {
-                            warn!(
-                                "Invalid search fields ignored: {:?}. Valid fields: {:?}",
-                                invalid_fields, self.search_fields
-                            );
-                        }
-
-                        (valid_fields, invalid_fields)
-                    } else {
-                        // Use static/default search fields
-                        debug!("Using default search fields: {:?}", self.search_fields);
-                        (self.search_fields.clone(), Vec::new())
-                    };
-
-                if !fields_to_search.is_empty() {
-                    let search_conditions: Vec<String> = fields_to_search
-                        .iter()
-                        .map(|field| {
-                            let _ = args.add(&search_pattern);
-                            // Handle NULL values: field IS NOT NULL AND field ILIKE pattern
-                            let condition = format!(
-                                "({} IS NOT NULL AND {} ILIKE ${})",
-                                field, field, param_count
-                            );
-                            param_count += 1;
-                            condition
-                        })
-                        .collect();
+        // Pagination with prepared statements
+        if params.cursor.is_some() {
+            // Fetch one extra row to know whether another page follows
+            let limit = clause.bind(SqlValue::Int((per_page + 1) as i64));
+            query.push_str(&format!(" LIMIT {}", limit));
+        } else {
+            let limit = clause.bind(SqlValue::Int(per_page as i64));
+            let offset = clause.bind(SqlValue::Int(offset as i64));
+            query.push_str(&format!(" LIMIT {} OFFSET {}", limit, offset));
+        }
 
-                    let search_clause = format!("({})", search_conditions.join(" OR "));
-                    conditions.push(search_clause.clone());
+        debug!("Built query: {}", query);
+        debug!("With parameters: {:?}", clause.params());
 
-                    debug!("Search clause added: {}", search_clause);
-                    debug!("Search pattern: {}", search_pattern);
-                } else {
-                    warn!(
-                        "No valid search fields available for search term: {}",
-                        search
-                    );
+        (query, clause.into_params(), page, per_page)
+    }
+
+    /// SQL text and bind values of the `COUNT(*)` query; same WHERE as [`Self::select_sql`].
+    pub fn count_sql(&self, params: &QueryParams) -> (String, Vec<SqlValue>) {
+        let mut query = format!("SELECT COUNT(*) as total FROM {}", self.table);
+
+        for join in &self.joins {
+            query.push_str(&format!(" {}", join));
+        }
+
+        let clause = self.compile_where(params);
+        query.push_str(&clause.to_sql());
+
+        debug!("Built count query: {}", query);
+        debug!("With parameters: {:?}", clause.params());
+
+        (query, clause.into_params())
+    }
+
+    /// `agg` entries Postgres would reject: `sum` and `avg` of a column that isn't
+    /// numeric. Only checked for columns of this table, once its types are known.
+    pub fn aggregate_errors(&self, params: &QueryParams) -> Vec<FieldError> {
+        let Some(columns) = &self.column_types else {
+            return Vec::new();
+        };
+        params
+            .agg
+            .iter()
+            .flat_map(|specs| specs.split(','))
+            .filter_map(Aggregate::parse)
+            .filter(|agg| agg.function.is_numeric())
+            .filter_map(|agg| {
+                let field = agg.field?;
+                let column_type = column_types::lookup(columns, &self.table, &field)?;
+                if column_type.category == 'N' || !self.filterable_fields.contains(&field) {
+                    return None;
+                }
+                Some(FieldError {
+                    error: format!(
+                        "{} needs a numeric column, not {}",
+                        agg.function.name(),
+                        column_type.name
+                    ),
+                    field,
+                })
+            })
+            .collect()
+    }
+
+    /// SQL text and bind values of the aggregate query: `group_by` columns (or
+    /// `date_trunc` buckets) and `agg` functions over filterable fields, with the same
+    /// WHERE as [`Self::select_sql`]. Returns the group and aggregate aliases too.
+    pub fn aggregate_sql(
+        &self,
+        params: &QueryParams,
+    ) -> (String, Vec<SqlValue>, Vec<String>, Vec<String>) {
+        let page = params.page.unwrap_or(1).max(1);
+        let per_page = params.per_page.unwrap_or(10).min(100); // Max 100 per page
+        let offset = (page - 1) * per_page;
+
+        let mut groups: Vec<GroupBy> = Vec::new();
+        for spec in params.group_by.iter().flat_map(|specs| specs.split(',')) {
+            match GroupBy::parse(spec) {
+                Some(group) if !self.filterable_fields.contains(&group.field) => {
+                    warn!("Group by field '{}' is not allowed", group.field)
+                }
+                Some(group) if !groups.iter().any(|g| g.alias() == group.alias()) => {
+                    groups.push(group)
                 }
+                Some(_) => {}
+                None => warn!("Invalid group_by entry ignored: '{}'", spec),
             }
         }
 
-        // Filter functionality with prepared statements
-        if let Some(filter) = &params.filter {
-            if let Ok(filters) = serde_json::from_str::<HashMap<String, serde_json::Value>>(filter)
-            {
-                for (key, value) in filters {
-                    if self.filterable_fields.contains(&key) {
-                        match value {
-                            serde_json::Value::String(s) => {
-                                let _ = args.add(s);
-                                conditions.push(format!("{} = ${}", key, param_count));
-                                param_count += 1;
-                            }
-                            serde_json::Value::Number(n) => {
-                                if let Some(i) = n.as_i64() {
-                                    let _ = args.add(i);
-                                    conditions.push(format!("{} = ${}", key, param_count));
-                                    param_count += 1;
-                                } else if let Some(f) = n.as_f64() {
-                                    let _ = args.add(f);
-                                    conditions.push(format!("{} = ${}", key, param_count));
-                                    param_count += 1;
-                                }
-                            }
-                            serde_json::Value::Bool(b) => {
-                                let _ = args.add(b);
-                                conditions.push(format!("{} = ${}", key, param_count));
-                                param_count += 1;
-                            }
-                            _ => {} // Skip complex types
-                        }
-                    } else {
-                        warn!("Filter field '{}' is not allowed: {:?}", key, value);
-                    }
+        let mut aggregates: Vec<Aggregate> = Vec::new();
+        for spec in params.agg.iter().flat_map(|specs| specs.split(',')) {
+            match Aggregate::parse(spec) {
+                Some(Aggregate {
+                    field: Some(field), ..
+                }) if !self.filterable_fields.contains(&field) => {
+                    warn!("Aggregate field '{}' is not allowed", field)
                 }
-            } else {
-                warn!("Failed to parse filter JSON: {}", filter);
+                Some(agg) if !aggregates.iter().any(|a| a.alias() == agg.alias()) => {
+                    aggregates.push(agg)
+                }
+                Some(_) => {}
+                None => warn!("Invalid agg entry ignored: '{}'", spec),
             }
         }
+        if aggregates.is_empty() {
+            aggregates.push(Aggregate::count());
+        }
+
+        let group_aliases: Vec<String> = groups.iter().map(GroupBy::alias).collect();
+        let aggregate_aliases: Vec<String> = aggregates.iter().map(Aggregate::alias).collect();
 
-        // Add WHERE clause
-        if !conditions.is_empty() {
-            query.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+        let select_fields: Vec<String> = groups
+            .iter()
+            .map(|group| format!("{} AS {}", group.to_sql(), group.alias()))
+            .chain(
+                aggregates
+                    .iter()
+                    .map(|agg| format!("{} AS {}", agg.to_sql(), agg.alias())),
+            )
+            .collect();
+
+        let mut query = format!("SELECT {} FROM {}", select_fields.join(", "), self.table);
+        for join in &self.joins {
+            query.push_str(&format!(" {}", join));
         }
 
-        // Sorting
-        let sort_by = params.sort_by.as_deref().unwrap_or("created_at");
-        let sort_order = params.sort_order.as_deref().unwrap_or("desc");
+        let mut clause = self.compile_where(params);
+        query.push_str(&clause.to_sql());
 
-        debug!(
-            "Sort requested - field: '{}', order: '{}'",
-            sort_by, sort_order
-        );
-        debug!("Available sortable fields: {:?}", self.sortable_fields);
+        if !groups.is_empty() {
+            let group_by: Vec<String> = groups.iter().map(GroupBy::to_sql).collect();
+            query.push_str(&format!(" GROUP BY {}", group_by.join(", ")));
+        }
 
-        if self.sortable_fields.contains(&sort_by.to_string()) {
-            let order = if sort_order.to_lowercase() == "asc" {
-                "ASC"
-            } else {
-                "DESC"
-            };
-            query.push_str(&format!(" ORDER BY {} {}", sort_by, order));
-            debug!("Applied sort: {} {}", sort_by, order);
-        } else {
-            warn!(
-                "Sort field '{}' is not allowed, falling back to default sort by 'created_at'",
-                sort_by
-            );
-            query.push_str(" ORDER BY created_at DESC");
+        // Sort by any result column, e.g. `sort=-count`; groups ascending otherwise
+        let mut order_by: Vec<String> = Vec::new();
+        for spec in params.sort.iter().flat_map(|specs| specs.split(',')) {
+            match SortKey::parse(spec) {
+                Some(key)
+                    if group_aliases.contains(&key.field)
+                        || aggregate_aliases.contains(&key.field) =>
+                {
+                    order_by.push(key.to_sql())
+                }
+                _ => warn!("Aggregate sort entry ignored: '{}'", spec),
+            }
+        }
+        if order_by.is_empty() {
+            order_by = group_aliases.clone();
+        }
+        if !order_by.is_empty() {
+            query.push_str(&format!(" ORDER BY {}", order_by.join(", ")));
         }
 
-        // Pagination with prepared statements
-        let _ = args.add(per_page as i64);
-        let _ = args.add(offset as i64);
-        query.push_str(&format!(
-            " LIMIT ${} OFFSET ${}",
-            param_count,
-            param_count + 1
-        ));
+        let limit = clause.bind(SqlValue::Int(per_page as i64));
+        let offset = clause.bind(SqlValue::Int(offset as i64));
+        query.push_str(&format!(" LIMIT {} OFFSET {}", limit, offset));
 
-        debug!("Built query: {}", query);
-        debug!("With parameters: {:?}", args);
+        debug!("Built aggregate query: {}", query);
+        debug!("With parameters: {:?}", clause.params());
 
-        (query, args, page, per_page)
+        (
+            query,
+            clause.into_params(),
+            group_aliases,
+            aggregate_aliases,
+        )
     }
 
-    pub fn build_count_query(&self, params: &QueryParams) -> (String, PgArguments) {
-        let mut query = format!("SELECT COUNT(*) as total FROM {}", self.table);
-
-        // Add static joins for count
-        for join in &self.joins {
-            query.push_str(&format!(" {}", join));
+    /// Compiles the trashed, search and filter conditions shared by the row and count
+    /// queries.
+    pub fn compile_where(&self, params: &QueryParams) -> WhereClause {
+        let mut clause = match &self.column_types {
+            Some(columns) => WhereClause::with_columns(&self.table, columns.clone()),
+            None => WhereClause::new(),
+        };
+        if let Some(column) = &self.soft_delete {
+            let column = format!("{}.{}", self.table, column);
+            clause.extend(Trashed::from_params(params).condition(&column));
         }
+        self.push_search_conditions(params, &mut clause);
+        self.push_filter_conditions(params, &mut clause);
+        self.push_exclude_conditions(params, &mut clause);
+        clause
+    }
+
+    // SELECT entries for a sparse fieldset; `id` and the sort columns are always
+    // fetched so cursors can be built, and dropped again when serializing
+    fn sparse_select(&self, fields: &[String], sort_keys: &[SortKey]) -> Vec<String> {
+        self.select_fields
+            .iter()
+            .filter(|select| {
+                let name = column_name(select);
+                name == "id"
+                    || fields.iter().any(|field| field == name)
+                    || sort_keys.iter().any(|key| key.column_name() == name)
+            })
+            .cloned()
+            .collect()
+    }
+
+    // Keys kept when serializing a sparse fieldset: the requested fields followed by
+    // the requested includes, which `fields=` must not prune
+    fn response_fields(&self, params: &QueryParams) -> Option<Arc<[String]>> {
+        let fields = resolve_fields(params.fields.as_deref(), &self.select_fields)?;
+        let mut fields = fields.to_vec();
+        fields.extend(
+            self.requested_includes(params)
+                .into_iter()
+                .map(|(name, _)| name.to_string()),
+        );
+        Some(fields.into())
+    }
 
-        // Add dynamic includes for count
-        if let Some(includes) = &params.include {
-            for include_name in includes.split(',') {
-                let include_name = include_name.trim();
-                if let Some(config) = self.include_relations.get(include_name) {
-                    query.push_str(&format!(" {}", config.join_clause));
+    // Include relations named in `include=`, in request order, skipping unknown names
+    fn requested_includes<'a>(
+        &'a self,
+        params: &'a QueryParams,
+    ) -> Vec<(&'a str, &'a IncludeConfig)> {
+        let Some(includes) = &params.include else {
+            return Vec::new();
+        };
+
+        let mut requested: Vec<(&str, &IncludeConfig)> = Vec::new();
+        for name in includes.split(',').map(str::trim) {
+            match self.include_relations.get(name) {
+                Some(config) if !requested.iter().any(|(existing, _)| *existing == name) => {
+                    debug!("Added include for: {}", name);
+                    requested.push((name, config));
                 }
+                Some(_) => {}
+                None if name.is_empty() => {}
+                None => warn!("Unknown include ignored: {}", name),
             }
         }
+        requested
+    }
 
-        let mut conditions = Vec::new();
-        let mut args = PgArguments::default();
-        let mut param_count = 1;
-
-        // Search functionality - SAME LOGIC AS build_query (FIXED!)
+    fn search_term<'a>(&self, params: &'a QueryParams) -> Option<&'a str> {
         let search_term = params.search_value.as_ref().or(params.search.as_ref());
+        search_term
+            .map(String::as_str)
+            .filter(|search| !search.is_empty())
+    }
 
-        if let Some(search) = search_term {
-            if !search.is_empty() {
-                let search_pattern = format!("%{}%", search);
-
-                // Determine which fields to search in (SAME AS build_query)
-                let (fields_to_search, _invalid_fields) =
-                    if let Some(dynamic_fields) = &params.search_fields {
-                        // Use dynamic fields from URL parameter
-                        let requested_fields: Vec<String> = dynamic_fields
-                            .split(',')
-                            .map(|s| s.trim().to_string())
-                            .collect();
-
-                        let valid_fields: Vec<String> = requested_fields
-                            .iter()
-                            .filter(|field| self.search_fields.contains(field))
-                            .cloned()
-                            .collect();
-
-                        let invalid_fields: Vec<String> = requested_fields
-                            .iter()
-                            .filter(|field| !self.search_fields.contains(field))
-                            .cloned()
-                            .collect();
-
-                        debug!(
-                            "Count query - Dynamic search - Requested fields: {:?}",
-                            requested_fields
-                        );
-                        debug!(
-                            "Count query - Dynamic search - Valid fields: {:?}",
-                            valid_fields
-                        );
-
-                        (valid_fields, invalid_fields)
-                    } else {
-                        // Use static/default search fields
-                        debug!(
-                            "Count query - Using default search fields: {:?}",
-                            self.search_fields
-                        );
-                        (self.search_fields.clone(), Vec::new())
-                    };
-
-                if !fields_to_search.is_empty() {
-                    let search_conditions: Vec<String> = fields_to_search
+    // `search_mode`, falling back to ILIKE when unknown or when fts has no tsvector column
+    fn search_mode(&self, params: &QueryParams) -> SearchMode {
+        let Some(name) = &params.search_mode else {
+            return SearchMode::ILike;
+        };
+        match SearchMode::parse(name) {
+            Some(SearchMode::FullText) if self.search_vector.is_none() => {
+                warn!("Full-text search is not available on {}", self.table);
+                SearchMode::ILike
+            }
+            Some(mode) => mode,
+            None => {
+                warn!("Unknown search mode ignored: {}", name);
+                SearchMode::ILike
+            }
+        }
+    }
+
+    // The requested `search_fields` that are searchable, otherwise every searchable field
+    fn fields_to_search(&self, params: &QueryParams) -> Vec<String> {
+        if let Some(dynamic_fields) = &params.search_fields {
+            let requested_fields: Vec<String> = dynamic_fields
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .collect();
+
+            let (valid_fields, invalid_fields): (Vec<String>, Vec<String>) = requested_fields
+                .into_iter()
+                .partition(|field| self.search_fields.contains(field));
+
+            debug!("Dynamic search - Valid fields: {:?}", valid_fields);
+
+            if !invalid_fields.is_empty() {
+                warn!(
+                    "Invalid search fields ignored: {:?}. Valid fields: {:?}",
+                    invalid_fields, self.search_fields
+                );
+            }
+
+            valid_fields
+        } else {
+            debug!("Using default search fields: {:?}", self.search_fields);
+            self.search_fields.clone()
+        }
+    }
+
+    // Search: `search_value` (or `search`) matched according to `search_mode`;
+    // ILIKE and fuzzy search the requested `search_fields`, fts the tsvector column
+    fn push_search_conditions(&self, params: &QueryParams, clause: &mut WhereClause) {
+        let Some(search) = self.search_term(params) else {
+            return;
+        };
+
+        match self.search_mode(params) {
+            SearchMode::FullText => {
+                let (Some(vector), Some(tsquery)) = (&self.search_vector, prefix_tsquery(search))
+                else {
+                    warn!("Search term has no searchable words: {}", search);
+                    return;
+                };
+                let placeholder = clause.bind(SqlValue::Text(tsquery));
+                clause.push(format!(
+                    "{} @@ to_tsquery('{}', {})",
+                    vector, TEXT_SEARCH_CONFIG, placeholder
+                ));
+            }
+            mode => {
+                let fields_to_search = self.fields_to_search(params);
+                if fields_to_search.is_empty() {
+                    warn!(
+                        "No valid search fields available for search term: {}",
+                        search
+                    );
+                    return;
+                }
+
+                let search_conditions: Vec<String> = if mode == SearchMode::Fuzzy {
+                    let placeholder = clause.bind(SqlValue::Text(search.to_string()));
+                    fields_to_search
+                        .iter()
+                        .map(|field| format!("{} % {}", field, placeholder))
+                        .collect()
+                } else {
+                    let search_pattern = format!("%{}%", search);
+                    fields_to_search
                         .iter()
                         .map(|field| {
-                            let _ = args.add(&search_pattern);
+                            let placeholder = clause.bind(SqlValue::Text(search_pattern.clone()));
                             // Handle NULL values: field IS NOT NULL AND field ILIKE pattern
-                            let condition = format!(
-                                "({} IS NOT NULL AND {} ILIKE ${})",
-                                field, field, param_count
-                            );
-                            param_count += 1;
-                            condition
+                            format!(
+                                "({} IS NOT NULL AND {} ILIKE {})",
+                                field, field, placeholder
+                            )
                         })
-                        .collect();
+                        .collect()
+                };
 
-                    let search_clause = format!("({})", search_conditions.join(" OR "));
-                    conditions.push(search_clause.clone());
+                clause.push(format!("({})", search_conditions.join(" OR ")));
+            }
+        }
+    }
 
-                    debug!("Count query - Search clause added: {}", search_clause);
+    // ORDER BY entry ranking fts and fuzzy matches, used when no explicit sort was
+    // requested; the requested or default sort then only breaks ties
+    fn relevance_order(&self, params: &QueryParams, clause: &mut WhereClause) -> Option<String> {
+        if params.sort.is_some() || params.sort_by.is_some() {
+            return None;
+        }
+        let search = self.search_term(params)?;
+
+        match self.search_mode(params) {
+            SearchMode::ILike => None,
+            SearchMode::FullText => {
+                let vector = self.search_vector.as_ref()?;
+                let placeholder = clause.bind(SqlValue::Text(prefix_tsquery(search)?));
+                Some(format!(
+                    "ts_rank({}, to_tsquery('{}', {})) DESC",
+                    vector, TEXT_SEARCH_CONFIG, placeholder
+                ))
+            }
+            SearchMode::Fuzzy => {
+                let fields = self.fields_to_search(params);
+                if fields.is_empty() {
+                    return None;
                 }
+                let placeholder = clause.bind(SqlValue::Text(search.to_string()));
+                let similarities: Vec<String> = fields
+                    .iter()
+                    .map(|field| format!("similarity({}, {})", field, placeholder))
+                    .collect();
+                Some(format!("GREATEST({}) DESC", similarities.join(", ")))
             }
         }
+    }
 
-        // Filter functionality (same as main query)
-        if let Some(filter) = &params.filter {
-            if let Ok(filters) = serde_json::from_str::<HashMap<String, serde_json::Value>>(filter)
-            {
-                for (key, value) in filters {
-                    if self.filterable_fields.contains(&key) {
-                        match value {
-                            serde_json::Value::String(s) => {
-                                let _ = args.add(s);
-                                conditions.push(format!("{} = ${}", key, param_count));
-                                param_count += 1;
-                            }
-                            serde_json::Value::Number(n) => {
-                                if let Some(i) = n.as_i64() {
-                                    let _ = args.add(i);
-                                    conditions.push(format!("{} = ${}", key, param_count));
-                                    param_count += 1;
-                                } else if let Some(f) = n.as_f64() {
-                                    let _ = args.add(f);
-                                    conditions.push(format!("{} = ${}", key, param_count));
-                                    param_count += 1;
-                                }
-                            }
-                            serde_json::Value::Bool(b) => {
-                                let _ = args.add(b);
-                                conditions.push(format!("{} = ${}", key, param_count));
-                                param_count += 1;
-                            }
-                            _ => {}
-                        }
+    // Resolve the ORDER BY keys from `sort` (or sortBy/sortOrder), keeping only
+    // sortable fields; cursor mode appends `id` as a unique tie-breaker
+    fn sort_keys(&self, params: &QueryParams) -> Vec<SortKey> {
+        let requested: Vec<SortKey> = match (&params.sort, &params.sort_by) {
+            (Some(sort), _) => sort
+                .split(',')
+                .filter(|spec| !spec.trim().is_empty())
+                .filter_map(|spec| {
+                    let key = SortKey::parse(spec);
+                    if key.is_none() {
+                        warn!("Invalid sort entry ignored: '{}'", spec);
                     }
-                }
+                    key
+                })
+                .collect(),
+            (None, Some(sort_by)) => {
+                let descending = params
+                    .sort_order
+                    .as_deref()
+                    .is_none_or(|order| !order.eq_ignore_ascii_case("asc"));
+                vec![SortKey::new(sort_by, descending)]
+            }
+            (None, None) => Vec::new(),
+        };
+
+        debug!("Sort requested: {:?}", requested);
+        debug!("Available sortable fields: {:?}", self.sortable_fields);
+
+        let mut keys: Vec<SortKey> = Vec::new();
+        for key in requested {
+            if !self.sortable_fields.contains(&key.field) {
+                warn!("Sort field '{}' is not allowed", key.field);
+            } else if !keys.iter().any(|existing| existing.field == key.field) {
+                keys.push(key);
             }
         }
 
-        if !conditions.is_empty() {
-            query.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+        if keys.is_empty() {
+            keys = self.default_sort.clone();
         }
 
-        debug!("Built count query: {}", query);
-        debug!("With parameters: {:?}", args);
+        if params.cursor.is_some() && !keys.iter().any(|key| key.column_name() == "id") {
+            let descending = keys.last().is_some_and(|key| key.descending);
+            keys.push(SortKey::not_null(&format!("{}.id", self.table), descending));
+        }
+
+        keys
+    }
+
+    // Decode the `cursor` token, ignoring tokens issued for a different sort order
+    fn decode_cursor(&self, params: &QueryParams, sort_keys: &[SortKey]) -> Option<Cursor> {
+        let token = params.cursor.as_deref().filter(|token| !token.is_empty())?;
+
+        match Cursor::decode(token) {
+            Some(cursor) if cursor.matches(sort_keys) => Some(cursor),
+            _ => {
+                warn!("Invalid or stale cursor ignored: {}", token);
+                None
+            }
+        }
+    }
+
+    // Parse the `filter` JSON (fields plus and/or/not groups) into WHERE conditions
+    fn push_filter_conditions(&self, params: &QueryParams, clause: &mut WhereClause) {
+        let Some(filter) = &params.filter else {
+            return;
+        };
 
-        (query, args)
+        match serde_json::from_str::<serde_json::Value>(filter) {
+            Ok(serde_json::Value::Object(filters)) => {
+                let conditions = compile_filter(&filters, &self.filterable_fields, clause);
+                clause.extend(conditions);
+            }
+            _ => warn!("Failed to parse filter JSON: {}", filter),
+        }
+    }
+
+    // Parse the `exclude` JSON, e.g. `{"id":["..."],"country":"United States"}`, into
+    // conditions dropping matching rows; `id` is always excludable
+    fn push_exclude_conditions(&self, params: &QueryParams, clause: &mut WhereClause) {
+        let Some(exclude) = &params.exclude else {
+            return;
+        };
+
+        let Ok(serde_json::Value::Object(excludes)) =
+            serde_json::from_str::<serde_json::Value>(exclude)
+        else {
+            warn!("Failed to parse exclude JSON: {}", exclude);
+            return;
+        };
+
+        for (key, value) in &excludes {
+            let field = if key == "id" {
+                format!("{}.id", self.table)
+            } else if self.filterable_fields.contains(key) {
+                key.clone()
+            } else {
+                warn!("Exclude field '{}' is not allowed: {:?}", key, value);
+                continue;
+            };
+
+            if let Some(condition) = compile_exclusion(&field, value, clause) {
+                clause.push(condition);
+            }
+        }
     }
 
     pub async fn execute<T>(
         &self,
         pool: &PgPool,
         params: &QueryParams,
-    ) -> Result<PaginatedResponse<T>, sqlx::Error>
+    ) -> Result<PaginatedResponse<Sparse<T>>, sqlx::Error>
     where
         T: for<'r> serde::de::Deserialize<'r> + Send + Unpin,
     {
@@ -536,33 +1092,72 @@ impl QueryBuilder {
         pool: &PgPool,
         params: &QueryParams,
         base_url: &str,
-    ) -> Result<PaginatedResponse<T>, sqlx::Error>
+    ) -> Result<PaginatedResponse<Sparse<T>>, sqlx::Error>
     where
         T: for<'r> serde::de::Deserialize<'r> + Send + Unpin,
     {
-        let (count_query, count_args) = self.build_count_query(params);
         let (query, args, page, per_page) = self.build_query(params);
-
-        // Execute count query
-        let total_row = sqlx::query_with(&count_query, count_args)
-            .fetch_one(pool)
-            .await?;
-        let total: i64 = total_row.get("total");
-        let total = total as u64;
+        let cursor_mode = params.cursor.is_some();
+
+        // Execute count query unless the caller opted out
+        let total = if params.with_count.unwrap_or(!cursor_mode) {
+            let (count_query, count_args) = self.build_count_query(params);
+            let total_row = sqlx::query_with(&count_query, count_args)
+                .fetch_one(pool)
+                .await?;
+            let total: i64 = total_row.get("total");
+            Some(total as u64)
+        } else {
+            None
+        };
 
         // Execute main query
         let rows = sqlx::query_with(&query, args).fetch_all(pool).await?;
 
-        // Convert rows to JSON and then deserialize to T
-        let mut data = Vec::new();
-        for row in rows {
-            let mut json_obj = serde_json::Map::new();
-            for column in row.columns() {
-                let column_name = column.name();
-                let value: serde_json::Value = self.row_value_to_json(&row, column_name);
-                json_obj.insert(column_name.to_string(), value);
+        // Convert rows to JSON objects
+        let mut objects: Vec<serde_json::Map<String, serde_json::Value>> =
+            rows.iter().map(|row| self.row_to_json(row)).collect();
+
+        let total_pages = total.map(|total| (total as f64 / per_page as f64).ceil() as u32);
+
+        let links = if cursor_mode {
+            let sort_keys = self.sort_keys(params);
+            let cursor = self.decode_cursor(params, &sort_keys);
+            let backwards = matches!(&cursor, Some(c) if c.direction == CursorDirection::Prev);
+
+            let has_more = objects.len() > per_page as usize;
+            objects.truncate(per_page as usize);
+            if backwards {
+                objects.reverse();
             }
 
+            // Walking forwards there is a previous page iff we came from a cursor,
+            // walking backwards there is always a next page
+            let (has_next, has_prev) = if backwards {
+                (true, has_more)
+            } else {
+                (has_more, cursor.is_some())
+            };
+
+            let next_cursor = objects
+                .last()
+                .filter(|_| has_next)
+                .map(|row| Cursor::from_row(CursorDirection::Next, &sort_keys, row).encode());
+            let prev_cursor = objects
+                .first()
+                .filter(|_| has_prev)
+                .map(|row| Cursor::from_row(CursorDirection::Prev, &sort_keys, row).encode());
+
+            self.generate_cursor_links(next_cursor, prev_cursor, per_page, base_url)
+        } else {
+            let has_more = objects.len() == per_page as usize;
+            self.generate_pagination_links(page, total_pages, per_page, has_more, base_url)
+        };
+
+        // Deserialize to T, serializing back only the requested fieldset
+        let fields = self.response_fields(params);
+        let mut data = Vec::new();
+        for json_obj in objects {
             let item: T =
                 serde_json::from_value(serde_json::Value::Object(json_obj)).map_err(|e| {
                     sqlx::Error::ColumnDecode {
@@ -570,13 +1165,9 @@ impl QueryBuilder {
                         source: Box::new(e),
                     }
                 })?;
-            data.push(item);
+            data.push(Sparse::new(item, fields.clone()));
         }
 
-        let total_pages = (total as f64 / per_page as f64).ceil() as u32;
-
-        let links = self.generate_pagination_links(page, total_pages, per_page, base_url);
-
         Ok(PaginatedResponse {
             count: total,
             page_context: PageContext {
@@ -589,6 +1180,36 @@ impl QueryBuilder {
         })
     }
 
+    pub async fn execute_aggregate(
+        &self,
+        pool: &PgPool,
+        params: &QueryParams,
+    ) -> Result<AggregateResponse, sqlx::Error> {
+        let (query, values, group_by, aggregates) = self.aggregate_sql(params);
+        let rows = sqlx::query_with(&query, into_arguments(values))
+            .fetch_all(pool)
+            .await?;
+
+        Ok(AggregateResponse {
+            group_by,
+            aggregates,
+            results: rows.iter().map(|row| self.row_to_json(row)).collect(),
+        })
+    }
+
+    fn row_to_json(
+        &self,
+        row: &sqlx::postgres::PgRow,
+    ) -> serde_json::Map<String, serde_json::Value> {
+        let mut json_obj = serde_json::Map::new();
+        for column in row.columns() {
+            let column_name = column.name();
+            let value: serde_json::Value = self.row_value_to_json(row, column_name);
+            json_obj.insert(column_name.to_string(), value);
+        }
+        json_obj
+    }
+
     fn row_value_to_json(
         &self,
         row: &sqlx::postgres::PgRow,
@@ -635,3 +1256,729 @@ impl QueryBuilder {
         serde_json::Value::Null
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::column_types::ColumnType;
+    use chrono::NaiveDate;
+
+    fn contacts() -> QueryBuilder {
+        QueryBuilder::new("contacts")
+            .select(vec!["id", "first_name", "city"])
+            .searchable(vec!["first_name", "last_name", "email"])
+            .filterable(vec![
+                "is_customer",
+                "is_supplier",
+                "city",
+                "country",
+                "email",
+                "created_at",
+                "score",
+            ])
+            .sortable(vec!["first_name", "last_name", "city", "created_at"])
+            .default_sort("-created_at")
+            .full_text("search_vector")
+            .include_relation(
+                "created_user",
+                IncludeConfig::one("users", "created_by", vec!["id", "first_name"]),
+            )
+    }
+
+    fn params() -> QueryParams {
+        QueryParams::default()
+    }
+
+    fn filter(json: &str) -> QueryParams {
+        QueryParams {
+            filter: Some(json.to_string()),
+            ..params()
+        }
+    }
+
+    fn text(s: &str) -> SqlValue {
+        SqlValue::Text(s.to_string())
+    }
+
+    #[test]
+    fn test_defaults_without_conditions() {
+        let (sql, values, page, per_page) = contacts().select_sql(&params());
+        assert_eq!(
+            sql,
+            "SELECT id, first_name, city FROM contacts ORDER BY created_at DESC LIMIT $1 OFFSET $2"
+        );
+        assert_eq!(values, vec![SqlValue::Int(10), SqlValue::Int(0)]);
+        assert_eq!((page, per_page), (1, 10));
+
+        let (count_sql, count_values) = contacts().count_sql(&params());
+        assert_eq!(count_sql, "SELECT COUNT(*) as total FROM contacts");
+        assert!(count_values.is_empty());
+    }
+
+    #[test]
+    fn test_pagination_offset_and_per_page_cap() {
+        let query = QueryParams {
+            page: Some(3),
+            per_page: Some(500),
+            ..params()
+        };
+        let (_, values, page, per_page) = contacts().select_sql(&query);
+        assert_eq!((page, per_page), (3, 100));
+        assert_eq!(values, vec![SqlValue::Int(100), SqlValue::Int(200)]);
+    }
+
+    #[test]
+    fn test_search_uses_default_fields() {
+        let query = QueryParams {
+            search: Some("jo".to_string()),
+            ..params()
+        };
+        let clause = contacts().compile_where(&query);
+        assert_eq!(
+            clause.to_sql(),
+            " WHERE ((first_name IS NOT NULL AND first_name ILIKE $1) \
+             OR (last_name IS NOT NULL AND last_name ILIKE $2) \
+             OR (email IS NOT NULL AND email ILIKE $3))"
+        );
+        assert_eq!(clause.params(), &[text("%jo%"), text("%jo%"), text("%jo%")]);
+    }
+
+    #[test]
+    fn test_search_value_and_search_fields_whitelisted() {
+        let query = QueryParams {
+            search: Some("ignored".to_string()),
+            search_value: Some("john".to_string()),
+            search_fields: Some("first_name, password_hash".to_string()),
+            ..params()
+        };
+        let clause = contacts().compile_where(&query);
+        assert_eq!(
+            clause.to_sql(),
+            " WHERE ((first_name IS NOT NULL AND first_name ILIKE $1))"
+        );
+        assert_eq!(clause.params(), &[text("%john%")]);
+    }
+
+    #[test]
+    fn test_search_without_valid_fields_is_skipped() {
+        let query = QueryParams {
+            search_value: Some("john".to_string()),
+            search_fields: Some("password_hash".to_string()),
+            ..params()
+        };
+        assert_eq!(contacts().compile_where(&query), WhereClause::new());
+
+        let empty = QueryParams {
+            search: Some(String::new()),
+            ..params()
+        };
+        assert_eq!(contacts().compile_where(&empty), WhereClause::new());
+    }
+
+    #[test]
+    fn test_scalar_filters_bind_typed_values() {
+        let clause = contacts().compile_where(&filter(
+            r#"{"city":"Jakarta","created_at":"2025-01-01","is_customer":true,"score":1.5}"#,
+        ));
+        assert_eq!(
+            clause.to_sql(),
+            " WHERE city = $1 AND created_at = $2 AND is_customer = $3 AND score = $4"
+        );
+        assert_eq!(
+            clause.params(),
+            &[
+                text("Jakarta"),
+                SqlValue::Date(NaiveDate::from_ymd_opt(2025, 1, 1).unwrap()),
+                SqlValue::Bool(true),
+                SqlValue::Float(1.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_operators() {
+        let clause = contacts().compile_where(&filter(
+            r#"{"created_at":{"gte":"2025-01-01T00:00:00Z","lt":"2025-02-01T00:00:00Z"},
+                "city":{"in":["Jakarta","Bandung"]},
+                "country":{"not_in":[]},
+                "email":{"is_null":false,"ilike":"%@example.com"},
+                "score":{"between":[1,5],"ne":3}}"#,
+        ));
+        assert_eq!(
+            clause.to_sql(),
+            " WHERE city IN ($1, $2) AND TRUE AND created_at >= $3 AND created_at < $4 \
+             AND email ILIKE $5 AND email IS NOT NULL AND score BETWEEN $6 AND $7 AND score <> $8"
+        );
+        assert_eq!(clause.params().len(), 8);
+        assert_eq!(clause.params()[0], text("Jakarta"));
+        assert!(matches!(clause.params()[2], SqlValue::Timestamp(_)));
+        assert_eq!(clause.params()[4], text("%@example.com"));
+        assert_eq!(clause.params()[7], SqlValue::Int(3));
+    }
+
+    #[test]
+    fn test_array_and_null_shorthands() {
+        let clause = contacts().compile_where(&filter(r#"{"city":["A","B"],"email":null}"#));
+        assert_eq!(clause.to_sql(), " WHERE city IN ($1, $2) AND email IS NULL");
+        assert_eq!(clause.params(), &[text("A"), text("B")]);
+    }
+
+    #[test]
+    fn test_malformed_filters_are_ignored() {
+        let clause = contacts().compile_where(&filter(
+            r#"{"password_hash":"x","city":{"between":[1],"gt":[1],"regex":"a"},"email":{"like":1}}"#,
+        ));
+        assert_eq!(clause, WhereClause::new());
+
+        assert_eq!(
+            contacts().compile_where(&filter("not json")),
+            WhereClause::new()
+        );
+        assert_eq!(contacts().compile_where(&filter("[1]")), WhereClause::new());
+    }
+
+    #[test]
+    fn test_full_text_search_ranks_by_relevance() {
+        let query = QueryParams {
+            search: Some("Jo  o'neil".to_string()),
+            search_mode: Some("fts".to_string()),
+            ..params()
+        };
+
+        let (sql, values, _, _) = contacts().select_sql(&query);
+        assert_eq!(
+            sql,
+            "SELECT id, first_name, city FROM contacts \
+             WHERE search_vector @@ to_tsquery('simple', $1) \
+             ORDER BY ts_rank(search_vector, to_tsquery('simple', $2)) DESC, created_at DESC \
+             LIMIT $3 OFFSET $4"
+        );
+        assert_eq!(
+            values[..2],
+            [text("jo:* & o:* & neil:*"), text("jo:* & o:* & neil:*")]
+        );
+
+        // An explicit sort replaces the ranking, the count query never ranks
+        let sorted = QueryParams {
+            sort: Some("first_name".to_string()),
+            ..query
+        };
+        let (sql, _, _, _) = contacts().select_sql(&sorted);
+        assert!(sql.ends_with("ORDER BY first_name ASC LIMIT $2 OFFSET $3"));
+        let (count_sql, _) = contacts().count_sql(&sorted);
+        assert_eq!(
+            count_sql,
+            "SELECT COUNT(*) as total FROM contacts WHERE search_vector @@ to_tsquery('simple', $1)"
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_search_and_mode_fallbacks() {
+        let query = QueryParams {
+            search_value: Some("jhon".to_string()),
+            search_fields: Some("first_name,last_name".to_string()),
+            search_mode: Some("fuzzy".to_string()),
+            ..params()
+        };
+        let (sql, values, _, _) = contacts().select_sql(&query);
+        assert_eq!(
+            sql,
+            "SELECT id, first_name, city FROM contacts \
+             WHERE (first_name % $1 OR last_name % $1) \
+             ORDER BY GREATEST(similarity(first_name, $2), similarity(last_name, $2)) DESC, \
+             created_at DESC LIMIT $3 OFFSET $4"
+        );
+        assert_eq!(values[..2], [text("jhon"), text("jhon")]);
+
+        // Without a tsvector column fts falls back to ILIKE, as do unknown modes
+        let users = QueryBuilder::new("users").searchable(vec!["username"]);
+        for mode in ["fts", "regex"] {
+            let query = QueryParams {
+                search: Some("jo".to_string()),
+                search_mode: Some(mode.to_string()),
+                ..params()
+            };
+            let (count_sql, _) = users.count_sql(&query);
+            assert_eq!(
+                count_sql,
+                "SELECT COUNT(*) as total FROM users \
+                 WHERE ((username IS NOT NULL AND username ILIKE $1))"
+            );
+        }
+    }
+
+    #[test]
+    fn test_aggregate_groups_buckets_and_sort() {
+        let query = QueryParams {
+            group_by: Some("country,created_at:month,secret,created_at:decade".to_string()),
+            agg: Some("count,sum:score,avg:password,median:score".to_string()),
+            filter: Some(r#"{"is_customer":true}"#.to_string()),
+            sort: Some("-count,unknown".to_string()),
+            ..params()
+        };
+
+        let (sql, values, groups, aggregates) = contacts().aggregate_sql(&query);
+        assert_eq!(
+            sql,
+            "SELECT country AS country, date_trunc('month', created_at) AS created_at_month, \
+             COUNT(*) AS count, SUM(score)::double precision AS sum_score \
+             FROM contacts WHERE is_customer = $1 \
+             GROUP BY country, date_trunc('month', created_at) \
+             ORDER BY count DESC LIMIT $2 OFFSET $3"
+        );
+        assert_eq!(
+            values,
+            vec![SqlValue::Bool(true), SqlValue::Int(10), SqlValue::Int(0)]
+        );
+        assert_eq!(groups, vec!["country", "created_at_month"]);
+        assert_eq!(aggregates, vec!["count", "sum_score"]);
+
+        // No groups and no aggregates: a single total count
+        let (sql, _, groups, aggregates) = contacts().aggregate_sql(&params());
+        assert_eq!(
+            sql,
+            "SELECT COUNT(*) AS count FROM contacts LIMIT $1 OFFSET $2"
+        );
+        assert!(groups.is_empty());
+        assert_eq!(aggregates, vec!["count"]);
+    }
+
+    #[test]
+    fn test_aggregate_aliases_and_numeric_columns() {
+        let columns = TableColumns::from([
+            ("score".to_string(), ColumnType::new("numeric(12,2)", 'N')),
+            (
+                "city".to_string(),
+                ColumnType::new("character varying(100)", 'S'),
+            ),
+        ]);
+        let builder = contacts()
+            .filterable(vec!["contacts.city", "city", "score", "contacts.score"])
+            .column_types(Arc::new(columns));
+        let query = QueryParams {
+            group_by: Some("contacts.city".to_string()),
+            agg: Some("sum:contacts.score,max:city".to_string()),
+            ..params()
+        };
+
+        // Qualified fields are aliased by their column name
+        let (sql, _, groups, aggregates) = builder.aggregate_sql(&query);
+        assert!(sql.starts_with(
+            "SELECT contacts.city AS city, SUM(contacts.score)::double precision AS sum_score, \
+             MAX(city) AS max_city FROM contacts"
+        ));
+        assert_eq!(groups, vec!["city"]);
+        assert_eq!(aggregates, vec!["sum_score", "max_city"]);
+        assert!(builder.aggregate_errors(&query).is_empty());
+
+        let query = QueryParams {
+            agg: Some("sum:city,avg:contacts.city,count:city,avg:score".to_string()),
+            ..params()
+        };
+        let errors: Vec<(String, String)> = builder
+            .aggregate_errors(&query)
+            .into_iter()
+            .map(|e| (e.field, e.error))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    "city".to_string(),
+                    "sum needs a numeric column, not character varying(100)".to_string()
+                ),
+                (
+                    "contacts.city".to_string(),
+                    "avg needs a numeric column, not character varying(100)".to_string()
+                ),
+            ]
+        );
+
+        // Without column types nothing is checked
+        assert!(contacts().aggregate_errors(&query).is_empty());
+    }
+
+    #[test]
+    fn test_exclude() {
+        let id = Uuid::new_v4();
+        let query = QueryParams {
+            filter: Some(r#"{"is_customer":true}"#.to_string()),
+            exclude: Some(format!(
+                r#"{{"id":["{}"],"country":"United States","email":null,"city":[],"last_name":"x"}}"#,
+                id
+            )),
+            ..params()
+        };
+        let clause = contacts().compile_where(&query);
+        assert_eq!(
+            clause.to_sql(),
+            " WHERE is_customer = $1 AND country IS DISTINCT FROM $2 AND email IS NOT NULL \
+             AND (contacts.id IS NULL OR contacts.id NOT IN ($3))"
+        );
+        assert_eq!(
+            clause.params(),
+            &[
+                SqlValue::Bool(true),
+                text("United States"),
+                SqlValue::Uuid(id)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_groups() {
+        let clause = contacts().compile_where(&filter(
+            r#"{"or":[{"is_customer":true},{"and":[{"is_supplier":true},{"country":"Indonesia"}]}],
+                "not":{"city":"Jakarta"}}"#,
+        ));
+        assert_eq!(
+            clause.to_sql(),
+            " WHERE NOT (city = $1) AND (is_customer = $2 OR (is_supplier = $3 AND country = $4))"
+        );
+        assert_eq!(
+            clause.params(),
+            &[
+                text("Jakarta"),
+                SqlValue::Bool(true),
+                SqlValue::Bool(true),
+                text("Indonesia"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search_and_filter_share_numbering_in_both_queries() {
+        let query = QueryParams {
+            search_value: Some("jo".to_string()),
+            search_fields: Some("email".to_string()),
+            filter: Some(r#"{"is_customer":true}"#.to_string()),
+            include: Some("created_user,unknown".to_string()),
+            ..params()
+        };
+
+        let (sql, values, _, _) = contacts().select_sql(&query);
+        assert_eq!(
+            sql,
+            "SELECT id, first_name, city, \
+             (SELECT json_build_object('id', created_user.id, 'first_name', created_user.first_name) \
+             FROM users created_user WHERE created_user.id = contacts.created_by) AS created_user \
+             FROM contacts WHERE ((email IS NOT NULL AND email ILIKE $1)) AND is_customer = $2 \
+             ORDER BY created_at DESC LIMIT $3 OFFSET $4"
+        );
+        assert_eq!(
+            values,
+            vec![
+                text("%jo%"),
+                SqlValue::Bool(true),
+                SqlValue::Int(10),
+                SqlValue::Int(0)
+            ]
+        );
+
+        let (count_sql, count_values) = contacts().count_sql(&query);
+        assert_eq!(
+            count_sql,
+            "SELECT COUNT(*) as total FROM contacts \
+             WHERE ((email IS NOT NULL AND email ILIKE $1)) AND is_customer = $2"
+        );
+        assert_eq!(count_values, values[..2].to_vec());
+    }
+
+    #[test]
+    fn test_has_many_include_is_aggregated_and_survives_fieldset() {
+        let users = QueryBuilder::new("users")
+            .select(vec!["id", "username", "email"])
+            .include_relation(
+                "created_contacts",
+                IncludeConfig::many("contacts", "created_by", vec!["id", "first_name"]),
+            );
+        let query = QueryParams {
+            fields: Some("username".to_string()),
+            include: Some("created_contacts".to_string()),
+            ..params()
+        };
+
+        let (sql, _, _, _) = users.select_sql(&query);
+        assert_eq!(
+            sql,
+            "SELECT id, username, \
+             (SELECT COALESCE(json_agg(json_build_object('id', created_contacts.id, \
+             'first_name', created_contacts.first_name)), '[]'::json) \
+             FROM contacts created_contacts WHERE created_contacts.created_by = users.id) \
+             AS created_contacts \
+             FROM users ORDER BY users.id ASC LIMIT $1 OFFSET $2"
+        );
+
+        let fields = users.response_fields(&query).unwrap();
+        assert_eq!(fields.to_vec(), vec!["username", "created_contacts"]);
+    }
+
+    #[test]
+    fn test_soft_deleted_rows_hidden_unless_trashed_requested() {
+        let contacts = contacts().soft_delete("deleted_at");
+        let trashed = |value: &str| QueryParams {
+            trashed: Some(value.to_string()),
+            ..filter(r#"{"is_customer":true}"#)
+        };
+
+        let (count_sql, _) = contacts.count_sql(&filter(r#"{"is_customer":true}"#));
+        assert_eq!(
+            count_sql,
+            "SELECT COUNT(*) as total FROM contacts \
+             WHERE contacts.deleted_at IS NULL AND is_customer = $1"
+        );
+
+        let (count_sql, _) = contacts.count_sql(&trashed("only"));
+        assert_eq!(
+            count_sql,
+            "SELECT COUNT(*) as total FROM contacts \
+             WHERE contacts.deleted_at IS NOT NULL AND is_customer = $1"
+        );
+
+        let (count_sql, _) = contacts.count_sql(&trashed("with"));
+        assert_eq!(
+            count_sql,
+            "SELECT COUNT(*) as total FROM contacts WHERE is_customer = $1"
+        );
+
+        // Unknown values fall back to hiding deleted rows
+        let (count_sql, _) = contacts.count_sql(&trashed("all"));
+        assert!(count_sql.contains("contacts.deleted_at IS NULL"));
+
+        // Without a soft-delete column the parameter does nothing
+        let (count_sql, _) = QueryBuilder::new("users").count_sql(&trashed("only"));
+        assert_eq!(count_sql, "SELECT COUNT(*) as total FROM users");
+
+        let include =
+            IncludeConfig::many("contacts", "created_by", vec!["id"]).without_trashed("deleted_at");
+        assert_eq!(
+            include.select_expr("created_contacts", "users"),
+            "(SELECT COALESCE(json_agg(json_build_object('id', created_contacts.id)), '[]'::json) \
+             FROM contacts created_contacts WHERE created_contacts.created_by = users.id \
+             AND created_contacts.deleted_at IS NULL) AS created_contacts"
+        );
+    }
+
+    #[test]
+    fn test_sort_whitelist_and_fallback() {
+        let query = QueryParams {
+            sort_by: Some("first_name".to_string()),
+            sort_order: Some("ASC".to_string()),
+            ..params()
+        };
+        let (sql, _, _, _) = contacts().select_sql(&query);
+        assert!(sql.contains(" ORDER BY first_name ASC LIMIT"));
+
+        let invalid = QueryParams {
+            sort_by: Some("password_hash".to_string()),
+            ..params()
+        };
+        let (sql, _, _, _) = contacts().select_sql(&invalid);
+        assert!(sql.contains(" ORDER BY created_at DESC LIMIT"));
+
+        // Without a configured default the table's id is used
+        let (sql, _, _, _) = QueryBuilder::new("accounts").select_sql(&invalid);
+        assert!(sql.contains(" ORDER BY accounts.id ASC LIMIT"));
+    }
+
+    #[test]
+    fn test_sort_key_parse() {
+        assert_eq!(
+            SortKey::parse(" -city:nulls_last"),
+            Some(SortKey {
+                field: "city".to_string(),
+                descending: true,
+                nulls_first: false,
+                nullable: true,
+            })
+        );
+        assert_eq!(SortKey::parse("+city"), Some(SortKey::new("city", false)));
+        assert_eq!(
+            SortKey::parse("city:nulls_first").map(|key| key.to_sql()),
+            Some("city ASC NULLS FIRST".to_string())
+        );
+        assert_eq!(SortKey::parse("-"), None);
+        assert_eq!(SortKey::parse("city:sideways"), None);
+        assert_eq!(SortKey::new("contacts.id", false).column_name(), "id");
+    }
+
+    #[test]
+    fn test_column_types_bind_filter_values_as_column_type() {
+        let columns = TableColumns::from([
+            ("id".to_string(), ColumnType::new("uuid", 'U')),
+            (
+                "city".to_string(),
+                ColumnType::new("character varying(100)", 'S'),
+            ),
+        ]);
+        let builder = contacts().column_types(Arc::new(columns));
+        let id = Uuid::new_v4();
+        let query = QueryParams {
+            filter: Some(format!(r#"{{"city":"{}","is_customer":true}}"#, id)),
+            exclude: Some(format!(r#"{{"id":"{}"}}"#, id)),
+            ..params()
+        };
+
+        let clause = builder.compile_where(&query);
+        assert_eq!(
+            clause.to_sql(),
+            " WHERE city = $1::character varying AND is_customer = $2 \
+             AND contacts.id IS DISTINCT FROM $3::uuid"
+        );
+        assert_eq!(
+            clause.params()[0],
+            SqlValue::Cast(id.to_string(), "character varying".to_string())
+        );
+        assert_eq!(clause.params()[1], SqlValue::Bool(true));
+    }
+
+    #[test]
+    fn test_multi_column_sort() {
+        let query = QueryParams {
+            sort: Some(
+                "-city:nulls_last, last_name,+first_name:nulls_first,password_hash,city".into(),
+            ),
+            sort_by: Some("created_at".to_string()),
+            ..params()
+        };
+        let (sql, _, _, _) = contacts().select_sql(&query);
+        assert!(sql.contains(
+            " ORDER BY city DESC NULLS LAST, last_name ASC, first_name ASC NULLS FIRST LIMIT"
+        ));
+
+        let invalid = QueryParams {
+            sort: Some("city:sideways".to_string()),
+            ..params()
+        };
+        let (sql, _, _, _) = contacts().select_sql(&invalid);
+        assert!(sql.contains(" ORDER BY created_at DESC LIMIT"));
+    }
+
+    #[test]
+    fn test_cursor_mode_multi_column_sort() {
+        let query = QueryParams {
+            cursor: Some(String::new()),
+            sort: Some("-city,first_name".to_string()),
+            ..params()
+        };
+        let (sql, _, _, _) = contacts().select_sql(&query);
+        assert!(sql.contains(" ORDER BY city DESC, first_name ASC, contacts.id ASC LIMIT $1"));
+    }
+
+    #[test]
+    fn test_sparse_fieldset_keeps_id_and_sort_columns() {
+        let query = QueryParams {
+            fields: Some("city,password_hash".to_string()),
+            sort: Some("first_name".to_string()),
+            ..params()
+        };
+        let (sql, _, _, _) = contacts().select_sql(&query);
+        assert!(sql.starts_with("SELECT id, first_name, city FROM contacts"));
+
+        let query = QueryParams {
+            fields: Some("city".to_string()),
+            ..params()
+        };
+        let (sql, _, _, _) = contacts().select_sql(&query);
+        assert!(sql.starts_with("SELECT id, city FROM contacts"));
+    }
+
+    #[test]
+    fn test_cursor_mode_first_page() {
+        let query = QueryParams {
+            cursor: Some(String::new()),
+            sort_by: Some("first_name".to_string()),
+            sort_order: Some("asc".to_string()),
+            ..params()
+        };
+        let (sql, values, _, _) = contacts().select_sql(&query);
+        assert_eq!(
+            sql,
+            "SELECT id, first_name, city FROM contacts \
+             ORDER BY first_name ASC, contacts.id ASC LIMIT $1"
+        );
+        assert_eq!(values, vec![SqlValue::Int(11)]);
+    }
+
+    #[test]
+    fn test_cursor_mode_next_and_prev_pages() {
+        let id = Uuid::new_v4();
+        let sort_keys = vec![
+            SortKey::new("first_name", false),
+            SortKey::not_null("contacts.id", false),
+        ];
+        let row = serde_json::json!({"id": id.to_string(), "first_name": "Jane"});
+        let row = row.as_object().unwrap();
+
+        let next = Cursor::from_row(CursorDirection::Next, &sort_keys, row);
+        let query = QueryParams {
+            cursor: Some(next.encode()),
+            sort_by: Some("first_name".to_string()),
+            sort_order: Some("asc".to_string()),
+            filter: Some(r#"{"is_customer":true}"#.to_string()),
+            ..params()
+        };
+        let (sql, values, _, _) = contacts().select_sql(&query);
+        assert_eq!(
+            sql,
+            "SELECT id, first_name, city FROM contacts WHERE is_customer = $1 \
+             AND ((first_name > $2 OR first_name IS NULL) OR (first_name = $3 AND contacts.id > $4)) \
+             ORDER BY first_name ASC, contacts.id ASC LIMIT $5"
+        );
+        assert_eq!(
+            values,
+            vec![
+                SqlValue::Bool(true),
+                text("Jane"),
+                text("Jane"),
+                SqlValue::Uuid(id),
+                SqlValue::Int(11),
+            ]
+        );
+
+        // The count query ignores the cursor
+        let (count_sql, _) = contacts().count_sql(&query);
+        assert!(count_sql.ends_with("WHERE is_customer = $1"));
+
+        let prev = Cursor::from_row(CursorDirection::Prev, &sort_keys, row);
+        let query = QueryParams {
+            cursor: Some(prev.encode()),
+            sort_by: Some("first_name".to_string()),
+            sort_order: Some("asc".to_string()),
+            ..params()
+        };
+        let (sql, _, _, _) = contacts().select_sql(&query);
+        assert_eq!(
+            sql,
+            "SELECT id, first_name, city FROM contacts \
+             WHERE (first_name < $1 OR (first_name = $2 AND contacts.id < $3)) \
+             ORDER BY first_name DESC, contacts.id DESC LIMIT $4"
+        );
+    }
+
+    #[test]
+    fn test_stale_cursor_is_ignored() {
+        let sort_keys = vec![
+            SortKey::new("created_at", true),
+            SortKey::not_null("contacts.id", true),
+        ];
+        let row = serde_json::json!({"id": Uuid::new_v4().to_string(), "created_at": null});
+        let cursor = Cursor::from_row(CursorDirection::Next, &sort_keys, row.as_object().unwrap());
+
+        // Issued for created_at, now sorting by first_name
+        let query = QueryParams {
+            cursor: Some(cursor.encode()),
+            sort_by: Some("first_name".to_string()),
+            ..params()
+        };
+        let (sql, _, _, _) = contacts().select_sql(&query);
+        assert!(!sql.contains("WHERE"));
+
+        let garbage = QueryParams {
+            cursor: Some("not-a-cursor".to_string()),
+            ..params()
+        };
+        let (sql, _, _, _) = contacts().select_sql(&garbage);
+        assert!(!sql.contains("WHERE"));
+    }
+}
@@ -0,0 +1,255 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    utils::session::{ClientInfo, SessionService},
+    AppError,
+};
+
+/// Lifetime of a refresh token; every refresh starts a new one.
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+/// Opaque refresh tokens, stored as SHA-256 hashes in `refresh_tokens`.
+///
+/// A login starts a token family, its session, and every refresh rotates to a
+/// new token in it. Presenting a token that was already rotated means it leaked, so the whole
+/// family is revoked and both the thief and the user have to log in again.
+pub struct RefreshTokenService;
+
+/// A refresh token just handed out, and whose it is.
+#[derive(Debug)]
+pub struct IssuedRefreshToken {
+    pub user_id: Uuid,
+    pub session_id: Uuid,
+    pub token: String,
+}
+
+/// What a presented refresh token is worth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenState {
+    Active,
+    Expired,
+    Revoked,
+    /// Already exchanged for a newer token
+    Reused,
+}
+
+impl TokenState {
+    pub fn of(
+        expires_at: DateTime<Utc>,
+        rotated_at: Option<DateTime<Utc>>,
+        revoked_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        if revoked_at.is_some() {
+            TokenState::Revoked
+        } else if rotated_at.is_some() {
+            TokenState::Reused
+        } else if expires_at <= now {
+            TokenState::Expired
+        } else {
+            TokenState::Active
+        }
+    }
+}
+
+impl RefreshTokenService {
+    /// Starts a session for `user_id` logging in from `client`, with the first
+    /// token of its family.
+    pub async fn issue(
+        db: &PgPool,
+        user_id: Uuid,
+        client: &ClientInfo,
+    ) -> Result<IssuedRefreshToken, AppError> {
+        let mut tx = db
+            .begin()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        let session_id = SessionService::start(&mut *tx, user_id, client).await?;
+        let token = Self::generate();
+        sqlx::query(
+            "INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at) \
+             VALUES ($1, $2, $3, $4)",
+        )
+        .bind(user_id)
+        .bind(session_id)
+        .bind(Self::hash(&token))
+        .bind(Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS))
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        Ok(IssuedRefreshToken {
+            user_id,
+            session_id,
+            token,
+        })
+    }
+
+    /// Exchanges `token` for a new one in the same family. A reused token
+    /// revokes its family's session.
+    pub async fn rotate(db: &PgPool, token: &str) -> Result<IssuedRefreshToken, AppError> {
+        let mut tx = db
+            .begin()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        // Locked, so two refreshes with the same token can't both rotate it
+        let row: Option<StoredToken> = sqlx::query_as(
+            "SELECT id, user_id, family_id, expires_at, rotated_at, revoked_at \
+             FROM refresh_tokens WHERE token_hash = $1 FOR UPDATE",
+        )
+        .bind(Self::hash(token))
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        let Some(stored) = row else {
+            return Err(AppError::UnAuthorized);
+        };
+
+        let now = Utc::now();
+        match TokenState::of(stored.expires_at, stored.rotated_at, stored.revoked_at, now) {
+            TokenState::Active => {}
+            TokenState::Reused => {
+                tracing::warn!(
+                    "Refresh token reused, revoking family {} of user {}",
+                    stored.family_id,
+                    stored.user_id
+                );
+                SessionService::revoke(&mut *tx, stored.family_id, None).await?;
+                tx.commit()
+                    .await
+                    .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+                return Err(AppError::UnAuthorized);
+            }
+            TokenState::Expired | TokenState::Revoked => return Err(AppError::UnAuthorized),
+        }
+
+        let next = Self::generate();
+        sqlx::query("UPDATE refresh_tokens SET rotated_at = $2 WHERE id = $1")
+            .bind(stored.id)
+            .bind(now)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        sqlx::query(
+            "INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at) \
+             VALUES ($1, $2, $3, $4)",
+        )
+        .bind(stored.user_id)
+        .bind(stored.family_id)
+        .bind(Self::hash(&next))
+        .bind(now + Duration::days(REFRESH_TOKEN_TTL_DAYS))
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        sqlx::query("UPDATE sessions SET last_seen_at = $2 WHERE id = $1")
+            .bind(stored.family_id)
+            .bind(now)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        Ok(IssuedRefreshToken {
+            user_id: stored.user_id,
+            session_id: stored.family_id,
+            token: next,
+        })
+    }
+
+    /// Revokes the session `token` belongs to; unknown tokens are ignored.
+    pub async fn revoke(db: &PgPool, token: &str) -> Result<(), AppError> {
+        let family_id: Option<Uuid> =
+            sqlx::query_scalar("SELECT family_id FROM refresh_tokens WHERE token_hash = $1")
+                .bind(Self::hash(token))
+                .fetch_optional(db)
+                .await
+                .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        if let Some(family_id) = family_id {
+            SessionService::revoke(db, family_id, None).await?;
+        }
+        Ok(())
+    }
+
+    // 256 random bits, URL-safe so the token fits a cookie or a header as is
+    fn generate() -> String {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    /// Hex SHA-256 of `token`, the form it is stored and looked up in.
+    pub fn hash(token: &str) -> String {
+        format!("{:x}", Sha256::digest(token.as_bytes()))
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct StoredToken {
+    id: Uuid,
+    user_id: Uuid,
+    family_id: Uuid,
+    expires_at: DateTime<Utc>,
+    rotated_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_tokens_are_random_and_hashed() {
+        let token = RefreshTokenService::generate();
+        assert_eq!(token.len(), 43);
+        assert_ne!(token, RefreshTokenService::generate());
+
+        let hash = RefreshTokenService::hash(&token);
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, RefreshTokenService::hash(&token));
+        assert_eq!(
+            RefreshTokenService::hash("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_token_state() {
+        let now = Utc::now();
+        let later = now + Duration::days(1);
+        let earlier = now - Duration::days(1);
+
+        assert_eq!(TokenState::of(later, None, None, now), TokenState::Active);
+        assert_eq!(
+            TokenState::of(earlier, None, None, now),
+            TokenState::Expired
+        );
+        assert_eq!(
+            TokenState::of(later, Some(earlier), None, now),
+            TokenState::Reused
+        );
+        // An expired token that was rotated is still a reuse
+        assert_eq!(
+            TokenState::of(earlier, Some(earlier), None, now),
+            TokenState::Reused
+        );
+        assert_eq!(
+            TokenState::of(later, Some(earlier), Some(now), now),
+            TokenState::Revoked
+        );
+    }
+}
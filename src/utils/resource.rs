@@ -0,0 +1,285 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+use sqlx::PgConnection;
+use std::future::Future;
+use uuid::Uuid;
+
+use crate::{
+    models::AuthenticatedUser,
+    utils::{
+        code_sequence::CodeSequence,
+        query_builder::{IncludeConfig, QueryBuilder},
+    },
+    AppError,
+};
+
+/// Prefix every resource is mounted under.
+pub const API_PREFIX: &str = "/api/v1";
+
+/// Column metadata of a model struct, generated by `#[derive(Resource)]` from
+/// `resource_derive`. [`Resource`] impls read their whitelists from it.
+pub trait ResourceFields {
+    const TABLE: &'static str;
+    /// Every column except `hidden` ones, in declaration order
+    const SELECT_FIELDS: &'static [&'static str];
+    const SEARCHABLE_FIELDS: &'static [&'static str];
+    const FILTERABLE_FIELDS: &'static [&'static str];
+    const SORTABLE_FIELDS: &'static [&'static str];
+    /// Columns a create may write
+    const CREATE_FIELDS: &'static [&'static str];
+    /// Columns an update may write
+    const UPDATE_FIELDS: &'static [&'static str];
+}
+
+/// Declarative description of a CRUD entity: its table, field whitelists and DTOs.
+///
+/// Implementors get list, get-by-id, create, update, upsert, delete, bulk and aggregate endpoints
+/// from `routes::resource::router`, and can be passed to the `CrudService` methods
+/// generic over a resource.
+///
+/// The `before_*`/`after_*` hooks run inside the write's transaction, on its
+/// connection: a `before_*` hook can change the values about to be written or
+/// reject the write, and an `after_*` hook sees the written row before the commit.
+/// An error from either rolls the whole write back and becomes the response.
+pub trait Resource: Send + Sync + 'static {
+    /// Row as read from the table by lists, get-by-id and `RETURNING *`
+    type Row: DeserializeOwned + Send + Sync + Unpin + 'static;
+    /// Body returned to clients for a row
+    type Response: Serialize + From<Self::Row> + Send + 'static;
+    type Create: DeserializeOwned + Send + 'static;
+    type Update: DeserializeOwned + Serialize + Send + 'static;
+
+    const TABLE: &'static str;
+    /// Route path under [`API_PREFIX`], e.g. `/contacts`
+    const PATH: &'static str;
+    const SELECT_FIELDS: &'static [&'static str];
+    const SEARCHABLE_FIELDS: &'static [&'static str] = &[];
+    /// Generated `tsvector` column enabling `search_mode=fts`
+    const SEARCH_VECTOR: Option<&'static str> = None;
+    const FILTERABLE_FIELDS: &'static [&'static str] = &[];
+    const SORTABLE_FIELDS: &'static [&'static str] = &[];
+    /// Columns `create_values` may produce; anything else is rejected with a 400
+    const CREATE_FIELDS: &'static [&'static str];
+    /// Members a create body may send, read into `Create`; anything else is
+    /// rejected with a 400 instead of being dropped
+    const CREATE_BODY_FIELDS: &'static [&'static str] = Self::CREATE_FIELDS;
+    /// Columns PUT, PATCH and bulk updates may write
+    const UPDATE_FIELDS: &'static [&'static str];
+    /// Natural keys with a unique index, each mounted as an upsert at
+    /// `PUT {PATH}/by-{key}/{value}`
+    const UPSERT_KEYS: &'static [&'static str] = &[];
+    /// Update fields `create_values` fills in itself, e.g. status flags. An upsert
+    /// that finds the row leaves them as they are.
+    const SERVER_DEFAULTS: &'static [&'static str] = &[];
+    /// Default order in `sort=` syntax; `None` sorts by `id`
+    const DEFAULT_SORT: Option<&'static str> = None;
+    const JOINS: &'static [&'static str] = &[];
+    /// Whether deletes only stamp `deleted_at`/`deleted_by`. Enables `trashed=`,
+    /// restore and purge; the table needs both columns.
+    const SOFT_DELETE: bool = false;
+    /// Numbering of the `code` column, assigned on insert to rows created without one
+    const CODE_SEQUENCE: Option<CodeSequence> = None;
+
+    /// Relations clients can request with `include=`.
+    fn includes() -> Vec<(&'static str, IncludeConfig)> {
+        Vec::new()
+    }
+
+    /// Stored form of natural key `key`'s `value`, e.g. a normalized email. An
+    /// upsert compares the key in its URL and body in this form.
+    fn upsert_key(key: &str, value: &str) -> String {
+        let _ = key;
+        value.to_string()
+    }
+
+    /// Column values to insert for a create request, with defaults filled in. Runs
+    /// on the blocking thread pool, so it may do CPU-heavy work like hashing.
+    fn create_values(data: Self::Create) -> Result<serde_json::Value, AppError>;
+
+    /// Before a create, upsert or bulk create inserts `values`, which already passed
+    /// the `CREATE_FIELDS` check.
+    fn before_create(
+        conn: &mut PgConnection,
+        auth: &AuthenticatedUser,
+        values: &mut Map<String, Value>,
+    ) -> impl Future<Output = Result<(), AppError>> + Send {
+        let _ = (conn, auth, values);
+        async { Ok(()) }
+    }
+
+    /// After a row is inserted.
+    fn after_create(
+        conn: &mut PgConnection,
+        auth: &AuthenticatedUser,
+        row: &Self::Row,
+    ) -> impl Future<Output = Result<(), AppError>> + Send {
+        let _ = (conn, auth, row);
+        async { Ok(()) }
+    }
+
+    /// Before a PUT, PATCH or bulk update writes `changes` to row `id`; `changes`
+    /// already passed the `UPDATE_FIELDS` check.
+    fn before_update(
+        conn: &mut PgConnection,
+        auth: &AuthenticatedUser,
+        id: Uuid,
+        changes: &mut Map<String, Value>,
+    ) -> impl Future<Output = Result<(), AppError>> + Send {
+        let _ = (conn, auth, id, changes);
+        async { Ok(()) }
+    }
+
+    /// After a row is updated, including by an upsert that found it.
+    fn after_update(
+        conn: &mut PgConnection,
+        auth: &AuthenticatedUser,
+        row: &Self::Row,
+    ) -> impl Future<Output = Result<(), AppError>> + Send {
+        let _ = (conn, auth, row);
+        async { Ok(()) }
+    }
+
+    /// Before row `id` is deleted (or soft-deleted), singly or in bulk. Restore
+    /// and purge don't run the delete hooks.
+    fn before_delete(
+        conn: &mut PgConnection,
+        auth: &AuthenticatedUser,
+        id: Uuid,
+    ) -> impl Future<Output = Result<(), AppError>> + Send {
+        let _ = (conn, auth, id);
+        async { Ok(()) }
+    }
+
+    /// After row `id` is deleted (or soft-deleted).
+    fn after_delete(
+        conn: &mut PgConnection,
+        auth: &AuthenticatedUser,
+        id: Uuid,
+    ) -> impl Future<Output = Result<(), AppError>> + Send {
+        let _ = (conn, auth, id);
+        async { Ok(()) }
+    }
+
+    /// Base URL used in pagination links.
+    fn base_url() -> String {
+        format!("{}{}", API_PREFIX, Self::PATH)
+    }
+
+    /// A [`QueryBuilder`] configured with this resource's metadata.
+    fn query_builder() -> QueryBuilder {
+        let mut builder = QueryBuilder::new(Self::TABLE)
+            .select(Self::SELECT_FIELDS.to_vec())
+            .searchable(Self::SEARCHABLE_FIELDS.to_vec())
+            .filterable(Self::FILTERABLE_FIELDS.to_vec())
+            .sortable(Self::SORTABLE_FIELDS.to_vec());
+
+        if let Some(sort) = Self::DEFAULT_SORT {
+            builder = builder.default_sort(sort);
+        }
+        if let Some(column) = Self::SEARCH_VECTOR {
+            builder = builder.full_text(column);
+        }
+        if Self::SOFT_DELETE {
+            builder = builder.soft_delete("deleted_at");
+        }
+        for join in Self::JOINS {
+            builder = builder.join(join);
+        }
+        for (name, config) in Self::includes() {
+            builder = builder.include_relation(name, config);
+        }
+
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use resource_derive::Resource;
+    use serde_json::json;
+
+    #[allow(dead_code)]
+    #[derive(Default, Resource)]
+    #[resource(
+        table = "products",
+        create = CreateProduct,
+        update = UpdateProduct,
+        response = ProductResponse,
+        computed(label: String)
+    )]
+    struct Product {
+        id: i64,
+        #[resource(searchable, sortable, create, update)]
+        name: String,
+        #[resource(filterable, sortable, create(default = 0.0), update)]
+        price: f64,
+        #[resource(filterable, create(default = "IDR"), skip_if_none)]
+        currency: Option<String>,
+        #[resource(hidden)]
+        secret: String,
+        #[resource(skip)]
+        tags: Vec<String>,
+        #[resource(skip)]
+        maker: Option<String>,
+    }
+
+    impl Product {
+        fn label(&self) -> String {
+            format!("{} ({})", self.name, self.price)
+        }
+    }
+
+    #[test]
+    fn test_derive_generates_fields_and_dtos() {
+        assert_eq!(Product::TABLE, "products");
+        assert_eq!(Product::SELECT_FIELDS, ["id", "name", "price", "currency"]);
+        assert_eq!(Product::SEARCHABLE_FIELDS, ["name"]);
+        assert_eq!(Product::FILTERABLE_FIELDS, ["price", "currency"]);
+        assert_eq!(Product::SORTABLE_FIELDS, ["name", "price"]);
+        assert_eq!(Product::CREATE_FIELDS, ["name", "price", "currency"]);
+        assert_eq!(Product::UPDATE_FIELDS, ["name", "price"]);
+
+        let create: CreateProduct = serde_json::from_value(json!({"name": "Pen"})).unwrap();
+        let values: serde_json::Value = create.into();
+        assert_eq!(
+            values,
+            json!({"name": "Pen", "price": 0.0, "currency": "IDR"})
+        );
+
+        let update = UpdateProduct {
+            name: None,
+            price: Some(2.5),
+        };
+        assert_eq!(
+            serde_json::to_value(update).unwrap(),
+            json!({"name": null, "price": 2.5})
+        );
+    }
+
+    #[test]
+    fn test_derive_generates_response() {
+        let product = Product {
+            id: 1,
+            name: "Pen".to_string(),
+            price: 2.5,
+            currency: None,
+            secret: "s3cret".to_string(),
+            tags: vec!["office".to_string()],
+            maker: None,
+        };
+        assert_eq!(
+            serde_json::to_value(ProductResponse::from(product)).unwrap(),
+            json!({"id": 1, "name": "Pen", "price": 2.5, "tags": ["office"], "label": "Pen (2.5)"})
+        );
+
+        let product = Product {
+            currency: Some("IDR".to_string()),
+            maker: Some("Acme".to_string()),
+            ..Product::default()
+        };
+        let response = serde_json::to_value(ProductResponse::from(product)).unwrap();
+        assert_eq!(response["currency"], "IDR");
+        assert_eq!(response["maker"], "Acme");
+    }
+}
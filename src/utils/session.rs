@@ -0,0 +1,238 @@
+use axum::http::{header::USER_AGENT, HeaderMap};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{PgExecutor, PgPool};
+use std::net::SocketAddr;
+use uuid::Uuid;
+
+use crate::AppError;
+
+/// Where a login came from, recorded on its session.
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl ClientInfo {
+    /// Client of a request from `peer`. Behind a proxy the first
+    /// `X-Forwarded-For` address wins; it is only shown to users, never trusted.
+    pub fn new(headers: &HeaderMap, peer: Option<SocketAddr>) -> Self {
+        let forwarded = headers
+            .get("x-forwarded-for")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').next())
+            .map(|ip| ip.trim().to_string())
+            .filter(|ip| !ip.is_empty());
+
+        Self {
+            ip_address: forwarded.or_else(|| peer.map(|addr| addr.ip().to_string())),
+            user_agent: headers
+                .get(USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+        }
+    }
+
+    /// Readable name of the client's device, e.g. `Firefox on Linux`.
+    pub fn device(&self) -> String {
+        let user_agent = self.user_agent.as_deref().unwrap_or_default();
+        // Most specific first: Edge and Opera also claim Chrome and Safari, Android
+        // claims Linux and iOS claims Mac OS X
+        let browser = [
+            ("Edg/", "Edge"),
+            ("OPR/", "Opera"),
+            ("Firefox/", "Firefox"),
+            ("Chrome/", "Chrome"),
+            ("Safari/", "Safari"),
+            ("curl/", "curl"),
+            ("PostmanRuntime/", "Postman"),
+        ]
+        .into_iter()
+        .find(|(token, _)| user_agent.contains(token))
+        .map(|(_, name)| name);
+        let os = [
+            ("Android", "Android"),
+            ("iPhone", "iOS"),
+            ("iPad", "iOS"),
+            ("Windows", "Windows"),
+            ("Mac OS X", "macOS"),
+            ("Linux", "Linux"),
+        ]
+        .into_iter()
+        .find(|(token, _)| user_agent.contains(token))
+        .map(|(_, name)| name);
+
+        match (browser, os) {
+            (Some(browser), Some(os)) => format!("{} on {}", browser, os),
+            (Some(name), None) | (None, Some(name)) => name.to_string(),
+            (None, None) => "Unknown device".to_string(),
+        }
+    }
+}
+
+/// An active login, as listed to its user.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Session {
+    pub id: Uuid,
+    pub device: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    /// Whether this is the session making the request
+    pub current: bool,
+}
+
+/// Login sessions in `sessions`, one per refresh token family.
+pub struct SessionService;
+
+impl SessionService {
+    /// Starts a session for `user_id` logging in from `client`.
+    pub async fn start<'c>(
+        executor: impl PgExecutor<'c>,
+        user_id: Uuid,
+        client: &ClientInfo,
+    ) -> Result<Uuid, AppError> {
+        sqlx::query_scalar(
+            "INSERT INTO sessions (user_id, device, ip_address, user_agent) \
+             VALUES ($1, $2, $3, $4) RETURNING id",
+        )
+        .bind(user_id)
+        .bind(client.device())
+        .bind(&client.ip_address)
+        .bind(&client.user_agent)
+        .fetch_one(executor)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))
+    }
+
+    /// Whether session `session_id` of `user_id` is still active, marking it as
+    /// seen. `last_seen_at` is written at most once a minute.
+    pub async fn touch(db: &PgPool, user_id: Uuid, session_id: Uuid) -> Result<bool, AppError> {
+        sqlx::query_scalar(
+            "WITH touched AS ( \
+                 UPDATE sessions SET last_seen_at = NOW() \
+                 WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL \
+                 AND last_seen_at < NOW() - INTERVAL '1 minute' \
+             ) \
+             SELECT EXISTS ( \
+                 SELECT 1 FROM sessions WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL \
+             )",
+        )
+        .bind(session_id)
+        .bind(user_id)
+        .fetch_one(db)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))
+    }
+
+    /// Active sessions of `user_id`, most recently seen first: not revoked, and
+    /// holding a refresh token that can still be used.
+    pub async fn list(db: &PgPool, user_id: Uuid, current: Uuid) -> Result<Vec<Session>, AppError> {
+        sqlx::query_as(
+            "SELECT s.id, s.device, s.ip_address, s.user_agent, s.created_at, s.last_seen_at, \
+                    s.id = $2 AS current \
+             FROM sessions s \
+             WHERE s.user_id = $1 AND s.revoked_at IS NULL AND EXISTS ( \
+                 SELECT 1 FROM refresh_tokens t WHERE t.family_id = s.id \
+                 AND t.rotated_at IS NULL AND t.revoked_at IS NULL AND t.expires_at > NOW() \
+             ) \
+             ORDER BY s.last_seen_at DESC",
+        )
+        .bind(user_id)
+        .bind(current)
+        .fetch_all(db)
+        .await
+        .map_err(|e| AppError::DatabaseError(e.to_string()))
+    }
+
+    /// Revokes session `session_id` and its refresh tokens; with `user_id`, only
+    /// if the session is theirs. `false` when there was no such active session.
+    pub async fn revoke<'c>(
+        executor: impl PgExecutor<'c>,
+        session_id: Uuid,
+        user_id: Option<Uuid>,
+    ) -> Result<bool, AppError> {
+        let query = Self::revoke_statement("id = $1 AND ($2::UUID IS NULL OR user_id = $2)");
+        let revoked: i64 = sqlx::query_scalar(&query)
+            .bind(session_id)
+            .bind(user_id)
+            .fetch_one(executor)
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+
+        Ok(revoked > 0)
+    }
+
+    /// Revokes every session of `user_id`, returning how many were active.
+    pub async fn revoke_all(db: &PgPool, user_id: Uuid) -> Result<i64, AppError> {
+        let query = Self::revoke_statement("user_id = $1");
+        sqlx::query_scalar(&query)
+            .bind(user_id)
+            .fetch_one(db)
+            .await
+            .map_err(|e| AppError::DatabaseError(e.to_string()))
+    }
+
+    // Revokes the active sessions matching `condition` together with their
+    // refresh tokens, selecting how many sessions it revoked
+    fn revoke_statement(condition: &str) -> String {
+        format!(
+            "WITH revoked AS ( \
+                 UPDATE sessions SET revoked_at = NOW() \
+                 WHERE {} AND revoked_at IS NULL RETURNING id \
+             ), tokens AS ( \
+                 UPDATE refresh_tokens SET revoked_at = NOW() \
+                 WHERE family_id IN (SELECT id FROM revoked) AND revoked_at IS NULL \
+             ) \
+             SELECT COUNT(*) FROM revoked",
+            condition
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(user_agent: &str) -> ClientInfo {
+        ClientInfo {
+            ip_address: None,
+            user_agent: Some(user_agent.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_client_info_prefers_forwarded_address() {
+        let peer: SocketAddr = "10.0.0.2:51234".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, "curl/8.5.0".parse().unwrap());
+
+        let info = ClientInfo::new(&headers, Some(peer));
+        assert_eq!(info.ip_address.as_deref(), Some("10.0.0.2"));
+        assert_eq!(info.user_agent.as_deref(), Some("curl/8.5.0"));
+
+        headers.insert("x-forwarded-for", "203.0.113.7, 10.0.0.1".parse().unwrap());
+        let info = ClientInfo::new(&headers, Some(peer));
+        assert_eq!(info.ip_address.as_deref(), Some("203.0.113.7"));
+    }
+
+    #[test]
+    fn test_device_names_browser_and_os() {
+        assert_eq!(
+            client("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36 Edg/126.0.0.0").device(),
+            "Edge on Windows"
+        );
+        assert_eq!(
+            client("Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.5 Mobile/15E148 Safari/604.1").device(),
+            "Safari on iOS"
+        );
+        assert_eq!(
+            client("Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Mobile Safari/537.36").device(),
+            "Chrome on Android"
+        );
+        assert_eq!(client("curl/8.5.0").device(), "curl");
+        assert_eq!(ClientInfo::default().device(), "Unknown device");
+    }
+}
@@ -0,0 +1,248 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::Value;
+use sqlx::{postgres::PgArguments, Arguments};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::utils::column_types::{self, ColumnType, TableColumns};
+
+/// A typed bind parameter produced while compiling a query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlValue {
+    Text(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Uuid(Uuid),
+    Timestamp(DateTime<Utc>),
+    Date(NaiveDate),
+    /// Text Postgres parses as the named type, bound as `$n::type`
+    Cast(String, String),
+}
+
+impl SqlValue {
+    /// Converts a scalar JSON value into a bind parameter.
+    ///
+    /// Strings that parse as a UUID, an RFC 3339 timestamp or an ISO date are
+    /// bound with their native type so they compare correctly against `uuid`,
+    /// `timestamptz` and `date` columns. Arrays, objects and `null` return `None`.
+    pub fn from_json(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(s) => Some(Self::from_text(s)),
+            Value::Number(n) => n
+                .as_i64()
+                .map(SqlValue::Int)
+                .or_else(|| n.as_f64().map(SqlValue::Float)),
+            Value::Bool(b) => Some(SqlValue::Bool(*b)),
+            _ => None,
+        }
+    }
+
+    fn from_text(s: &str) -> Self {
+        if let Ok(uuid) = Uuid::parse_str(s) {
+            return SqlValue::Uuid(uuid);
+        }
+        if let Ok(timestamp) = DateTime::parse_from_rfc3339(s) {
+            return SqlValue::Timestamp(timestamp.with_timezone(&Utc));
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            return SqlValue::Date(date);
+        }
+        SqlValue::Text(s.to_string())
+    }
+
+    pub fn add_to(self, args: &mut PgArguments) {
+        let _ = match self {
+            SqlValue::Text(v) => args.add(v),
+            SqlValue::Int(v) => args.add(v),
+            SqlValue::Float(v) => args.add(v),
+            SqlValue::Bool(v) => args.add(v),
+            SqlValue::Uuid(v) => args.add(v),
+            SqlValue::Timestamp(v) => args.add(v),
+            SqlValue::Date(v) => args.add(v),
+            SqlValue::Cast(v, _) => args.add(v),
+        };
+    }
+}
+
+/// WHERE conditions plus the parameters they bind, in placeholder order.
+///
+/// Both the row query and the count query compile their filters into one of
+/// these, so they always agree on the conditions and on the `$n` numbering.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct WhereClause {
+    conditions: Vec<String>,
+    params: Vec<SqlValue>,
+    // Queried table and its column types, when known
+    columns: Option<(String, Arc<TableColumns>)>,
+}
+
+impl WhereClause {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A clause over `table` whose values compared with its columns are bound as
+    /// the column type, see [`Self::operand`].
+    pub fn with_columns(table: &str, columns: Arc<TableColumns>) -> Self {
+        Self {
+            columns: Some((table.to_string(), columns)),
+            ..Self::default()
+        }
+    }
+
+    /// Registers a bind parameter and returns its `$n` placeholder.
+    pub fn bind(&mut self, value: SqlValue) -> String {
+        self.params.push(value);
+        match self.params.last() {
+            Some(SqlValue::Cast(_, type_name)) => format!("${}::{}", self.params.len(), type_name),
+            _ => format!("${}", self.params.len()),
+        }
+    }
+
+    /// Bind parameter for comparing `field` with a scalar JSON value.
+    ///
+    /// When the column type of `field` is known the value is sent as text cast to
+    /// it, so `"2025-01-01"` is a date against a date column and plain text against
+    /// a varchar one. Otherwise [`SqlValue::from_json`] guesses the type.
+    pub fn operand(&self, field: &str, value: &Value) -> Option<SqlValue> {
+        let text = match value {
+            Value::String(s) => s.clone(),
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            _ => return None,
+        };
+        match self
+            .column_type(field)
+            .and_then(ColumnType::comparison_type)
+        {
+            Some(type_name) => Some(SqlValue::Cast(text, type_name)),
+            None => SqlValue::from_json(value),
+        }
+    }
+
+    // Type of `field`, a column of the queried table, bare or qualified with it
+    fn column_type(&self, field: &str) -> Option<&ColumnType> {
+        let (table, columns) = self.columns.as_ref()?;
+        column_types::lookup(columns, table, field)
+    }
+
+    pub fn push(&mut self, condition: String) {
+        self.conditions.push(condition);
+    }
+
+    pub fn extend(&mut self, conditions: impl IntoIterator<Item = String>) {
+        self.conditions.extend(conditions);
+    }
+
+    pub fn params(&self) -> &[SqlValue] {
+        &self.params
+    }
+
+    /// The ` WHERE ...` suffix, or an empty string when there are no conditions.
+    pub fn to_sql(&self) -> String {
+        if self.conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", self.conditions.join(" AND "))
+        }
+    }
+
+    pub fn into_params(self) -> Vec<SqlValue> {
+        self.params
+    }
+}
+
+pub fn into_arguments(params: Vec<SqlValue>) -> PgArguments {
+    let mut args = PgArguments::default();
+    for value in params {
+        value.add_to(&mut args);
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn contact_columns() -> Arc<TableColumns> {
+        Arc::new(TableColumns::from([
+            ("id".to_string(), ColumnType::new("uuid", 'U')),
+            (
+                "code".to_string(),
+                ColumnType::new("character varying(20)", 'S'),
+            ),
+            (
+                "created_at".to_string(),
+                ColumnType::new("timestamp with time zone", 'D'),
+            ),
+        ]))
+    }
+
+    #[test]
+    fn test_from_json_guesses_type_of_text() {
+        let id = Uuid::new_v4();
+        assert_eq!(
+            SqlValue::from_json(&json!(id.to_string())),
+            Some(SqlValue::Uuid(id))
+        );
+        assert!(matches!(
+            SqlValue::from_json(&json!("2025-01-31T10:00:00+07:00")),
+            Some(SqlValue::Timestamp(_))
+        ));
+        assert_eq!(
+            SqlValue::from_json(&json!("2025-01-31")),
+            Some(SqlValue::Date(
+                NaiveDate::from_ymd_opt(2025, 1, 31).unwrap()
+            ))
+        );
+        assert_eq!(
+            SqlValue::from_json(&json!("Jakarta")),
+            Some(SqlValue::Text("Jakarta".to_string()))
+        );
+        assert_eq!(SqlValue::from_json(&json!(3)), Some(SqlValue::Int(3)));
+        assert_eq!(SqlValue::from_json(&json!(null)), None);
+        assert_eq!(SqlValue::from_json(&json!([1])), None);
+    }
+
+    #[test]
+    fn test_operand_binds_as_column_type() {
+        let mut clause = WhereClause::with_columns("contacts", contact_columns());
+
+        // A date-like code is still compared as text
+        let code = clause.operand("code", &json!("2025-01-31")).unwrap();
+        assert_eq!(clause.bind(code), "$1::character varying");
+        let created = clause.operand("created_at", &json!("2025-01-31")).unwrap();
+        assert_eq!(clause.bind(created), "$2::timestamp with time zone");
+        let id = clause.operand("contacts.id", &json!(Uuid::nil())).unwrap();
+        assert_eq!(clause.bind(id), "$3::uuid");
+        assert_eq!(
+            clause.params()[0],
+            SqlValue::Cast("2025-01-31".to_string(), "character varying".to_string())
+        );
+
+        // Columns of other tables and unknown columns fall back to guessing
+        assert_eq!(
+            clause.operand("users.code", &json!("2025-01-31")),
+            SqlValue::from_json(&json!("2025-01-31"))
+        );
+        assert_eq!(clause.operand("city", &json!(1)), Some(SqlValue::Int(1)));
+        assert_eq!(clause.operand("code", &json!(null)), None);
+    }
+
+    #[test]
+    fn test_to_sql_joins_conditions() {
+        let mut clause = WhereClause::new();
+        assert_eq!(clause.to_sql(), "");
+
+        let placeholder = clause.bind(SqlValue::Bool(true));
+        clause.push(format!("is_customer = {}", placeholder));
+        clause.extend(["deleted_at IS NULL".to_string()]);
+        assert_eq!(
+            clause.to_sql(),
+            " WHERE is_customer = $1 AND deleted_at IS NULL"
+        );
+        assert_eq!(clause.into_params(), vec![SqlValue::Bool(true)]);
+    }
+}
@@ -0,0 +1,89 @@
+#!/bin/bash
+
+# Test registration, email verification and resend against a running server
+# started with MAIL_BACKEND=file (and the default MAIL_DIR=mail)
+BASE_URL="http://127.0.0.1:5001"
+MAIL_DIR="${MAIL_DIR:-mail}"
+SUFFIX=$(date +%s)
+EMAIL="Reg.Test.$SUFFIX@Example.com"
+LOWER_EMAIL=$(echo "$EMAIL" | tr '[:upper:]' '[:lower:]')
+FAILED=0
+
+check() {
+    if [ "$2" == "$3" ]; then
+        echo "   ✅ $1"
+    else
+        echo "   ❌ $1: expected $3, got $2"
+        FAILED=1
+    fi
+}
+
+# Latest verification link sent to $1, with quoted-printable soft breaks undone
+latest_token() {
+    local file
+    file=$(grep -l "$1" "$MAIL_DIR"/*.eml 2>/dev/null | xargs -r ls -t | head -1)
+    [ -n "$file" ] || return
+    sed -e ':a;/=$/{N;s/=\n//;ba}' -e 's/=3D/=/g' "$file" | grep -o 'token=[A-Za-z0-9._-]*' | head -1 | cut -d= -f2
+}
+
+echo "🧪 Testing Registration Flow"
+echo "============================"
+
+echo "1. Rejecting an invalid body..."
+STATUS=$(curl -s -o /dev/null -w "%{http_code}" -X POST "$BASE_URL/api/auth/register" \
+  -H "Content-Type: application/json" \
+  -d '{"username": " ", "email": "not-an-email", "password": "short"}')
+check "invalid fields answer 400" "$STATUS" "400"
+
+echo "2. Registering $EMAIL..."
+FIRST=$(curl -s -w "\n%{http_code}" -X POST "$BASE_URL/api/auth/register" \
+  -H "Content-Type: application/json" \
+  -d "{\"username\": \"reg_$SUFFIX\", \"email\": \"$EMAIL\", \"password\": \"password123\"}")
+check "registration answers 201" "$(echo "$FIRST" | tail -1)" "201"
+
+echo "3. Registering the same address again, in another case..."
+SECOND=$(curl -s -w "\n%{http_code}" -X POST "$BASE_URL/api/auth/register" \
+  -H "Content-Type: application/json" \
+  -d "{\"username\": \"reg2_$SUFFIX\", \"email\": \"$LOWER_EMAIL\", \"password\": \"password123\"}")
+check "a taken address gets the same answer" "$SECOND" "$FIRST"
+
+echo "4. Logging in before verifying, with the address in another case..."
+STATUS=$(curl -s -o /dev/null -w "%{http_code}" -X POST "$BASE_URL/api/auth" \
+  -H "Content-Type: application/json" \
+  -d "{\"email\": \"$LOWER_EMAIL\", \"password\": \"password123\"}")
+echo "   login answered $STATUS (403 with REQUIRE_VERIFIED_EMAIL=true, else 200)"
+
+echo "5. Resending the verification link..."
+RESEND=$(curl -s -X POST "$BASE_URL/api/auth/verify-email/resend" \
+  -H "Content-Type: application/json" \
+  -d "{\"email\": \"$EMAIL\"}")
+UNKNOWN=$(curl -s -X POST "$BASE_URL/api/auth/verify-email/resend" \
+  -H "Content-Type: application/json" \
+  -d "{\"email\": \"nobody.$SUFFIX@example.com\"}")
+check "unknown addresses get the same answer" "$UNKNOWN" "$RESEND"
+
+echo "6. Verifying with the emailed token..."
+TOKEN=$(latest_token "$LOWER_EMAIL")
+check "a verification link was emailed" "$([ -n "$TOKEN" ] && echo yes)" "yes"
+STATUS=$(curl -s -o /dev/null -w "%{http_code}" -X POST "$BASE_URL/api/auth/verify-email" \
+  -H "Content-Type: application/json" \
+  -d "{\"token\": \"$TOKEN\"}")
+check "verification answers 200" "$STATUS" "200"
+
+STATUS=$(curl -s -o /dev/null -w "%{http_code}" -X POST "$BASE_URL/api/auth/verify-email" \
+  -H "Content-Type: application/json" \
+  -d '{"token": "not-a-token"}')
+check "a bad token answers 400" "$STATUS" "400"
+
+echo "7. Logging in after verifying..."
+STATUS=$(curl -s -o /dev/null -w "%{http_code}" -X POST "$BASE_URL/api/auth" \
+  -H "Content-Type: application/json" \
+  -d "{\"email\": \"$EMAIL\", \"password\": \"password123\"}")
+check "login answers 200" "$STATUS" "200"
+
+if [ $FAILED -eq 0 ]; then
+    echo -e "\n✅ Registration flow test completed!"
+else
+    echo -e "\n❌ Registration flow test failed"
+    exit 1
+fi